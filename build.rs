@@ -0,0 +1,13 @@
+use std::{env, fs, path::PathBuf};
+
+/// Bundle the vega-lite schema, if it has been downloaded, for validating specs.
+fn main() {
+    let source = "src/vega-lite-schema.json";
+    println!("cargo:rerun-if-changed={source}");
+    let target = PathBuf::from(env::var("OUT_DIR").unwrap()).join("vega-lite-schema.json");
+    let schema = fs::read(source).unwrap_or_else(|_| {
+        println!("cargo:warning={source} not found, run download-vega.nu to validate specs");
+        Vec::new()
+    });
+    fs::write(target, schema).unwrap();
+}
@@ -0,0 +1,522 @@
+//! A nushell plugin providing the `vega` commands of `vega.nu`,
+//! taking nushell tables directly rather than through `to json`.
+
+use clap::ValueEnum;
+use nu_plugin::{
+    serve_plugin, DynamicCompletionCall, EngineInterface, EvaluatedCall, MsgPackSerializer, Plugin,
+    PluginCommand, SimplePluginCommand,
+};
+use nu_protocol::{
+    engine::ArgType, Category, DynamicSuggestion, LabeledError, Record, Signature, Span,
+    SyntaxShape, Type, Value,
+};
+use serde_json::Value as Json;
+use std::{
+    io::{ErrorKind, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+use vega_view::{
+    chart::{self, Chart},
+    vegalite::Aggregate,
+};
+
+struct VegaPlugin;
+
+impl Plugin for VegaPlugin {
+    fn version(&self) -> String {
+        env!("CARGO_PKG_VERSION").into()
+    }
+
+    fn commands(&self) -> Vec<Box<dyn PluginCommand<Plugin = Self>>> {
+        vec![
+            Box::new(View),
+            Box::new(Bar),
+            Box::new(Series),
+            Box::new(Scatter),
+            Box::new(Flip),
+        ]
+    }
+}
+
+/// Display a Vega visualization of the input data in a new window.
+struct View;
+
+impl SimplePluginCommand for View {
+    type Plugin = VegaPlugin;
+
+    fn name(&self) -> &str {
+        "vega view"
+    }
+
+    fn description(&self) -> &str {
+        "Display a Vega visualization of the input data in a new window."
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("vega view")
+            .input_output_types(vec![
+                (Type::table(), Type::Nothing),
+                (Type::table(), Type::table()),
+            ])
+            .optional(
+                "spec",
+                SyntaxShape::Record(vec![].into()),
+                "a vega-lite specification (default is inferred from the data)",
+            )
+            .named("title", SyntaxShape::String, "title for the window", None)
+            .named("width", SyntaxShape::Int, "width of the window", None)
+            .named("height", SyntaxShape::Int, "height of the window", None)
+            .switch(
+                "select",
+                "return the rows picked with the chart's selection parameters",
+                None,
+            )
+            .category(Category::Viewers)
+    }
+
+    fn run(
+        &self,
+        _plugin: &VegaPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let select = call.has_flag("select")?;
+        let mut command = Command::new(vega_view_bin(engine)?);
+        command.current_dir(engine.get_current_dir()?);
+        for flag in ["title", "width", "height"] {
+            if let Some(value) = call.get_flag::<Value>(flag)? {
+                command.arg(format!("--{flag}")).arg(value.coerce_string()?);
+            }
+        }
+        if select {
+            // the picked rows are taken from the input, keeping their nushell types
+            command.arg("--select-index");
+        }
+        if let Some(spec) = call.opt::<Value>(0)? {
+            let mut spec = to_json(&spec);
+            // vega specs have a data array, which vega-view links to the data
+            let vega = spec["$schema"]
+                .as_str()
+                .is_some_and(|schema| schema.contains("/schema/vega/"));
+            if !vega {
+                spec["data"] = serde_json::json!({ "url": "/data" });
+            }
+            command.arg(spec.to_string());
+        }
+        let failed = |e: std::io::Error| {
+            LabeledError::new(format!("unable to run vega-view: {e}"))
+                .with_label("while running this", call.head)
+        };
+        let mut child = command
+            .stdin(Stdio::piped())
+            // stdout carries the plugin protocol
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(failed)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // vega-view may exit before reading its input, which its exit status explains
+            match stdin.write_all(to_json(input).to_string().as_bytes()) {
+                Err(e) if e.kind() != ErrorKind::BrokenPipe => return Err(failed(e)),
+                _ => {}
+            }
+        }
+        let output = child.wait_with_output().map_err(failed)?;
+        if !output.status.success() {
+            return Err(
+                LabeledError::new(format!("vega-view failed, {}", output.status))
+                    .with_label("while running this", call.head),
+            );
+        }
+        if !select {
+            return Ok(Value::nothing(call.head));
+        }
+        let indices = serde_json::from_slice::<Vec<usize>>(&output.stdout).map_err(|e| {
+            LabeledError::new(format!("unable to read the selected rows: {e}"))
+                .with_label("while running this", call.head)
+        })?;
+        Ok(Value::list(selected_rows(input, indices), call.head))
+    }
+}
+
+/// The rows of the input at the indices picked in vega-view, skipping any out of range.
+fn selected_rows(input: &Value, indices: Vec<usize>) -> Vec<Value> {
+    match input {
+        Value::List { vals, .. } => indices
+            .into_iter()
+            .filter_map(|i| vals.get(i).cloned())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// A vega-lite specification for a bar graph.
+struct Bar;
+
+impl SimplePluginCommand for Bar {
+    type Plugin = VegaPlugin;
+
+    fn name(&self) -> &str {
+        "vega bar"
+    }
+
+    fn description(&self) -> &str {
+        "vega-lite specification for a bar graph"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("vega bar")
+            .input_output_type(Type::Nothing, Type::record())
+            .required(
+                "value",
+                SyntaxShape::String,
+                "field name for the bar height",
+            )
+            .named(
+                "category",
+                SyntaxShape::String,
+                "field to discriminate different bars",
+                None,
+            )
+            .named(
+                "subcategory",
+                SyntaxShape::String,
+                "field to discriminate stacked bar sections",
+                None,
+            )
+            .named(
+                "aggregate",
+                SyntaxShape::String,
+                "how to combine values for a bar or bar section (default sum)",
+                None,
+            )
+            .category(Category::Viewers)
+    }
+
+    fn run(
+        &self,
+        _plugin: &VegaPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let chart = Chart::Bar {
+            value: call.req(0)?,
+            category: call.get_flag("category")?,
+            subcategory: call.get_flag("subcategory")?,
+            aggregate: match call.get_flag::<String>("aggregate")? {
+                Some(name) => Aggregate::from_str(&name, true).map_err(|_| {
+                    LabeledError::new(format!("unknown aggregate '{name}'"))
+                        .with_label("while running this", call.head)
+                })?,
+                None => Aggregate::Sum,
+            },
+        };
+        Ok(from_json(&chart.spec(), call.head))
+    }
+
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &VegaPlugin,
+        _engine: &EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(name) if name == "aggregate" => Some(
+                Aggregate::value_variants()
+                    .iter()
+                    .filter_map(|aggregate| aggregate.to_possible_value())
+                    .map(|value| DynamicSuggestion {
+                        value: value.get_name().into(),
+                        display_override: None,
+                        description: None,
+                        extra: None,
+                        append_whitespace: true,
+                        match_indices: None,
+                        span: None,
+                        kind: None,
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// A vega-lite specification for a time series plot.
+struct Series;
+
+impl SimplePluginCommand for Series {
+    type Plugin = VegaPlugin;
+
+    fn name(&self) -> &str {
+        "vega series"
+    }
+
+    fn description(&self) -> &str {
+        "vega-lite specification for a time series plot"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("vega series")
+            .input_output_type(Type::Nothing, Type::record())
+            .required(
+                "value",
+                SyntaxShape::String,
+                "field name for the series values",
+            )
+            .required("time", SyntaxShape::String, "field for time values")
+            .named(
+                "category",
+                SyntaxShape::String,
+                "field to discriminate different series",
+                None,
+            )
+            .switch("area", "render as a stacked area plot", None)
+            .category(Category::Viewers)
+    }
+
+    fn run(
+        &self,
+        _plugin: &VegaPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let chart = Chart::Series {
+            value: call.req(0)?,
+            time: call.req(1)?,
+            category: call.get_flag("category")?,
+            area: call.has_flag("area")?,
+        };
+        Ok(from_json(&chart.spec(), call.head))
+    }
+}
+
+/// A vega-lite specification for a scatter plot.
+struct Scatter;
+
+impl SimplePluginCommand for Scatter {
+    type Plugin = VegaPlugin;
+
+    fn name(&self) -> &str {
+        "vega scatter"
+    }
+
+    fn description(&self) -> &str {
+        "vega-lite specification for a scatter plot"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("vega scatter")
+            .input_output_type(Type::Nothing, Type::record())
+            .required(
+                "value",
+                SyntaxShape::String,
+                "field name for the y coordinate of a point in the plot",
+            )
+            .required(
+                "domain",
+                SyntaxShape::String,
+                "field for the x coordinate of a point in the plot",
+            )
+            .named(
+                "category",
+                SyntaxShape::String,
+                "field for the category of the point in the plot",
+                None,
+            )
+            .category(Category::Viewers)
+    }
+
+    fn run(
+        &self,
+        _plugin: &VegaPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let chart = Chart::Scatter {
+            value: call.req(0)?,
+            domain: call.req(1)?,
+            category: call.get_flag("category")?,
+        };
+        Ok(from_json(&chart.spec(), call.head))
+    }
+}
+
+/// Swap the x and y axes of a vega-lite specification.
+struct Flip;
+
+impl SimplePluginCommand for Flip {
+    type Plugin = VegaPlugin;
+
+    fn name(&self) -> &str {
+        "vega flip"
+    }
+
+    fn description(&self) -> &str {
+        "swap the x and y axis of a vega-lite specification"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("vega flip")
+            .input_output_type(Type::record(), Type::record())
+            .category(Category::Viewers)
+    }
+
+    fn run(
+        &self,
+        _plugin: &VegaPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let mut spec = to_json(input);
+        chart::flip(&mut spec);
+        Ok(from_json(&spec, call.head))
+    }
+}
+
+/// The vega-view executable: `$env.vega_view_bin`, else the one installed
+/// alongside the plugin, else the one on the path.
+fn vega_view_bin(engine: &EngineInterface) -> Result<PathBuf, LabeledError> {
+    if let Some(bin) = engine.get_env_var("vega_view_bin")? {
+        return Ok(PathBuf::from(bin.coerce_string()?));
+    }
+    let beside = std::env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name(format!("vega-view{}", std::env::consts::EXE_SUFFIX)))
+        .filter(|path| path.exists());
+    Ok(beside.unwrap_or_else(|| PathBuf::from("vega-view")))
+}
+
+/// Convert a nushell value to JSON for vega.
+/// Dates become RFC 3339 strings, durations nanoseconds and file sizes bytes.
+fn to_json(value: &Value) -> Json {
+    match value {
+        Value::Bool { val, .. } => Json::Bool(*val),
+        Value::Int { val, .. } => (*val).into(),
+        Value::Float { val, .. } => {
+            serde_json::Number::from_f64(*val).map_or(Json::Null, Json::Number)
+        }
+        Value::Filesize { val, .. } => val.get().into(),
+        Value::Duration { val, .. } => (*val).into(),
+        Value::Date { val, .. } => Json::String(val.to_rfc3339()),
+        Value::String { val, .. } => Json::String(val.clone()),
+        Value::Record { val, .. } => Json::Object(
+            val.iter()
+                .map(|(name, value)| (name.clone(), to_json(value)))
+                .collect(),
+        ),
+        Value::List { vals, .. } => Json::Array(vals.iter().map(to_json).collect()),
+        _ => Json::Null,
+    }
+}
+
+/// Convert JSON to a nushell value.
+fn from_json(json: &Json, span: Span) -> Value {
+    match json {
+        Json::Null => Value::nothing(span),
+        Json::Bool(b) => Value::bool(*b, span),
+        Json::Number(n) => match n.as_i64() {
+            Some(i) => Value::int(i, span),
+            None => Value::float(n.as_f64().unwrap_or(f64::NAN), span),
+        },
+        Json::String(s) => Value::string(s.clone(), span),
+        Json::Array(items) => Value::list(items.iter().map(|v| from_json(v, span)).collect(), span),
+        Json::Object(map) => Value::record(
+            map.iter()
+                .map(|(name, value)| (name.clone(), from_json(value, span)))
+                .collect::<Record>(),
+            span,
+        ),
+    }
+}
+
+fn main() {
+    serve_plugin(&VegaPlugin, MsgPackSerializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_protocol::record;
+    use serde_json::json;
+
+    #[test]
+    fn to_vega() {
+        let date = chrono::DateTime::parse_from_rfc3339("2024-03-01T12:00:00+01:00").unwrap();
+        let row = Value::test_record(record! {
+            "name" => Value::test_string("a"),
+            "count" => Value::test_int(3),
+            "ratio" => Value::test_float(0.5),
+            "size" => Value::test_filesize(2048),
+            "took" => Value::test_duration(1_000_000),
+            "when" => Value::test_date(date),
+            "ok" => Value::test_bool(true),
+            "none" => Value::test_nothing(),
+        });
+        assert_eq!(
+            to_json(&Value::test_list(vec![row])),
+            json!([{
+                "name": "a",
+                "count": 3,
+                "ratio": 0.5,
+                "size": 2048,
+                "took": 1_000_000,
+                "when": "2024-03-01T12:00:00+01:00",
+                "ok": true,
+                "none": null,
+            }])
+        );
+        assert_eq!(to_json(&Value::test_float(f64::NAN)), Json::Null);
+    }
+
+    #[test]
+    fn from_vega() {
+        let spec = json!({
+            "mark": {"type": "bar", "tooltip": true},
+            "encoding": {"x": {"field": "a", "bin": null}},
+            "layer": [1, 2.5, "c"],
+        });
+        let value = from_json(&spec, Span::test_data());
+        assert_eq!(
+            value,
+            Value::test_record(record! {
+                "mark" => Value::test_record(record! {
+                    "type" => Value::test_string("bar"),
+                    "tooltip" => Value::test_bool(true),
+                }),
+                "encoding" => Value::test_record(record! {
+                    "x" => Value::test_record(record! {
+                        "field" => Value::test_string("a"),
+                        "bin" => Value::test_nothing(),
+                    }),
+                }),
+                "layer" => Value::test_list(vec![
+                    Value::test_int(1),
+                    Value::test_float(2.5),
+                    Value::test_string("c"),
+                ]),
+            })
+        );
+        assert_eq!(to_json(&value), spec);
+    }
+
+    #[test]
+    fn selection() {
+        let rows: Vec<_> = (0..4).map(|i| Value::test_int(i * 10)).collect();
+        let input = Value::test_list(rows);
+        assert_eq!(
+            selected_rows(&input, vec![2, 0, 7]),
+            vec![Value::test_int(20), Value::test_int(0)]
+        );
+        assert!(selected_rows(&input, Vec::new()).is_empty());
+        assert!(selected_rows(&Value::test_int(1), vec![0]).is_empty());
+    }
+}
@@ -0,0 +1,137 @@
+use crate::vegalite::{
+    self as vl, Aggregate, Axis, Channel, Encoding, FieldType, Mark, MarkType, Size,
+};
+use clap::Subcommand;
+use serde_json::Value;
+
+/// A preset chart, built the same way as the charts of `vega.nu`.
+#[derive(Subcommand, Clone, Debug)]
+pub enum Chart {
+    /// A bar graph.
+    Bar {
+        /// The field name for the bar height.
+        value: String,
+
+        /// The field to discriminate different bars.
+        #[arg(long)]
+        category: Option<String>,
+
+        /// The field to discriminate stacked bar sections.
+        #[arg(long)]
+        subcategory: Option<String>,
+
+        /// How to combine values for a bar or bar section.
+        #[arg(long, value_enum, default_value_t = Aggregate::Sum)]
+        aggregate: Aggregate,
+    },
+
+    /// A time series plot.
+    Series {
+        /// The field name for the series values.
+        value: String,
+
+        /// The field for time values.
+        time: String,
+
+        /// The field to discriminate different series.
+        #[arg(long)]
+        category: Option<String>,
+
+        /// Render as a stacked area plot.
+        #[arg(long)]
+        area: bool,
+    },
+
+    /// A scatter plot.
+    Scatter {
+        /// The field name for the y coordinate of a point in the plot.
+        value: String,
+
+        /// The field for the x coordinate of a point in the plot.
+        domain: String,
+
+        /// The field for the category of the point in the plot.
+        #[arg(long)]
+        category: Option<String>,
+    },
+}
+
+impl Chart {
+    /// The vega-lite specification for the chart, without data.
+    pub fn spec(&self) -> Value {
+        self.chart().to_value()
+    }
+
+    /// The typed vega-lite specification for the chart, without data.
+    pub fn chart(&self) -> vl::Chart {
+        let (mark, encoding) = match self {
+            Self::Bar {
+                value,
+                category,
+                subcategory,
+                aggregate,
+            } => {
+                let mut encoding = Encoding::new();
+                if let Some(category) = category {
+                    encoding = encoding.x(Channel::field(category, FieldType::Nominal));
+                }
+                encoding = encoding.y(Channel::field(value, FieldType::Quantitative)
+                    .aggregate(*aggregate)
+                    .axis(Axis::titled(format!("{aggregate} of {value}"))));
+                if let Some(subcategory) = subcategory {
+                    encoding = encoding.color(Channel::field(subcategory, FieldType::Nominal));
+                }
+                (MarkType::Bar, encoding)
+            }
+            Self::Series {
+                value,
+                time,
+                category,
+                area,
+            } => {
+                let mut encoding = Encoding::new()
+                    .x(Channel::field(time, FieldType::Temporal))
+                    .y(Channel::field(value, FieldType::Quantitative));
+                if let Some(category) = category {
+                    encoding = encoding.color(Channel::field(category, FieldType::Nominal));
+                }
+                match area {
+                    true => (MarkType::Area, encoding),
+                    false => (MarkType::Line, encoding),
+                }
+            }
+            Self::Scatter {
+                value,
+                domain,
+                category,
+            } => {
+                let mut encoding = Encoding::new()
+                    .x(Channel::field(domain, FieldType::Quantitative))
+                    .y(Channel::field(value, FieldType::Quantitative));
+                if let Some(category) = category {
+                    encoding = encoding
+                        .color(Channel::field(category, FieldType::Nominal))
+                        .shape(Channel::field(category, FieldType::Nominal));
+                }
+                (MarkType::Point, encoding)
+            }
+        };
+        vl::Chart::new(Mark::new(mark).tooltip(true))
+            .width(Size::Container)
+            .encoding(encoding)
+    }
+}
+
+/// Swap the x and y axes of a specification, as `flip` does in `vega.nu`.
+pub fn flip(spec: &mut Value) {
+    if let Some(Value::Object(encoding)) = spec.get_mut("encoding") {
+        *encoding = std::mem::take(encoding)
+            .into_iter()
+            .map(|(channel, value)| match channel.as_str() {
+                "x" => ("y".to_string(), value),
+                "y" => ("x".to_string(), value),
+                _ => (channel, value),
+            })
+            .collect();
+    }
+}
@@ -0,0 +1,675 @@
+use crate::spec;
+use arrow::{
+    datatypes::SchemaRef,
+    error::ArrowError,
+    ipc::{
+        reader::{FileReader, StreamReader},
+        writer::FileWriter,
+    },
+    json::ArrayWriter,
+    record_batch::{RecordBatch, RecordBatchReader},
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use clap::ValueEnum;
+use parquet::{
+    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ProjectionMask},
+    errors::ParquetError,
+};
+use rusqlite::{types::ValueRef, Connection, OpenFlags};
+use serde_json::{Map, Number, Value};
+use std::{
+    fmt,
+    io::{self, Cursor},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// The formats accepted for data to visualize.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// A JSON array of records, served as-is.
+    Json,
+    /// Comma separated values with a header row.
+    Csv,
+    /// Tab separated values with a header row.
+    Tsv,
+    /// Newline delimited JSON, one record per line.
+    Ndjson,
+    /// Apache Arrow IPC, in the file (feather) or stream format.
+    Arrow,
+    /// Apache Parquet.
+    Parquet,
+}
+
+impl Format {
+    /// The format implied by the extension of a data file, if any.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "json" => Some(Self::Json),
+            "csv" => Some(Self::Csv),
+            "tsv" | "tab" => Some(Self::Tsv),
+            "ndjson" | "jsonl" => Some(Self::Ndjson),
+            "arrow" | "arrows" | "feather" | "ipc" => Some(Self::Arrow),
+            "parquet" | "pq" => Some(Self::Parquet),
+            _ => None,
+        }
+    }
+}
+
+/// A named data file, given on the command line as `name=path`.
+#[derive(Clone, Debug)]
+pub struct Dataset {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl FromStr for Dataset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((name, path)) if !name.is_empty() && !path.is_empty() => Ok(Self {
+                name: name.to_string(),
+                path: PathBuf::from(path),
+            }),
+            _ => Err(format!("expected name=path, found '{s}'")),
+        }
+    }
+}
+
+/// A failure to convert input data to JSON.
+#[derive(Debug)]
+pub enum Error {
+    Csv(csv::Error),
+    Json(serde_json::Error),
+    Line(usize, serde_json::Error),
+    Arrow(ArrowError),
+    Parquet(ParquetError),
+    NotColumnar(Format),
+    Sqlite(rusqlite::Error),
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Csv(e) => write!(f, "invalid delimited data: {e}"),
+            Self::Json(e) => write!(f, "unable to produce JSON: {e}"),
+            Self::Line(n, e) => write!(f, "invalid JSON record on line {n}: {e}"),
+            Self::Arrow(e) => write!(f, "invalid arrow data: {e}"),
+            Self::Parquet(e) => write!(f, "invalid parquet data: {e}"),
+            Self::NotColumnar(format) => {
+                write!(f, "{format:?} data cannot be served as arrow")
+            }
+            Self::Sqlite(e) => write!(f, "sqlite query failed: {e}"),
+            Self::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(e: csv::Error) -> Self {
+        Self::Csv(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+impl From<ArrowError> for Error {
+    fn from(e: ArrowError) -> Self {
+        Self::Arrow(e)
+    }
+}
+
+impl From<ParquetError> for Error {
+    fn from(e: ParquetError) -> Self {
+        Self::Parquet(e)
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Sqlite(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Convert input bytes in the given format to a JSON array of records.
+/// Columnar formats are projected onto the columns the specification refers to.
+pub fn to_json(format: Format, bytes: Vec<u8>, spec: &Value) -> Result<Vec<u8>, Error> {
+    match format {
+        Format::Json => Ok(bytes),
+        Format::Csv => delimited(b',', &bytes),
+        Format::Tsv => delimited(b'\t', &bytes),
+        Format::Ndjson => lines(&bytes),
+        Format::Arrow | Format::Parquet => {
+            let (_, batches) = columnar(format, bytes, spec)?;
+            let mut writer = ArrayWriter::new(Vec::new());
+            writer.write_batches(&batches.iter().collect::<Vec<_>>())?;
+            writer.finish()?;
+            let body = writer.into_inner();
+            // an empty table is written as nothing at all
+            Ok(if body.is_empty() {
+                b"[]".to_vec()
+            } else {
+                body
+            })
+        }
+    }
+}
+
+/// Convert columnar input bytes to the Arrow IPC file format, for use with
+/// vega's arrow loader. The data is projected as for `to_json`.
+/// An empty table is written as its schema with no batches.
+pub fn to_arrow(format: Format, bytes: Vec<u8>, spec: &Value) -> Result<Vec<u8>, Error> {
+    let (schema, batches) = columnar(format, bytes, spec)?;
+    let mut writer = FileWriter::try_new(Vec::new(), &schema)?;
+    for batch in &batches {
+        writer.write(batch)?;
+    }
+    writer.finish()?;
+    Ok(writer.into_inner()?)
+}
+
+/// The names of the columns of input bytes in the given format, read from the
+/// header or schema without converting the data.  JSON records are parsed to
+/// collect their keys.
+pub fn columns(format: Format, bytes: &[u8]) -> Result<Vec<String>, Error> {
+    let names = |schema: SchemaRef| schema.fields().iter().map(|f| f.name().clone()).collect();
+    match format {
+        Format::Json => Ok(keys(&serde_json::from_slice::<Vec<Value>>(bytes)?)),
+        Format::Ndjson => Ok(keys(&serde_json::from_slice::<Vec<Value>>(&lines(bytes)?)?)),
+        Format::Csv | Format::Tsv => {
+            let delimiter = if format == Format::Csv { b',' } else { b'\t' };
+            let mut reader = csv::ReaderBuilder::new()
+                .delimiter(delimiter)
+                .from_reader(bytes);
+            Ok(reader.headers()?.iter().map(String::from).collect())
+        }
+        Format::Arrow if bytes.starts_with(b"ARROW1") => Ok(names(
+            FileReader::try_new(Cursor::new(bytes), None)?.schema(),
+        )),
+        Format::Arrow => Ok(names(
+            StreamReader::try_new(Cursor::new(bytes), None)?.schema(),
+        )),
+        Format::Parquet => {
+            let bytes = bytes::Bytes::copy_from_slice(bytes);
+            Ok(names(
+                ParquetRecordBatchReaderBuilder::try_new(bytes)?
+                    .schema()
+                    .clone(),
+            ))
+        }
+    }
+}
+
+/// The keys of the records, in the order they are first seen.
+fn keys(records: &[Value]) -> Vec<String> {
+    let mut keys = Vec::<String>::new();
+    for key in records
+        .iter()
+        .filter_map(Value::as_object)
+        .flat_map(Map::keys)
+    {
+        if !keys.contains(key) {
+            keys.push(key.clone());
+        }
+    }
+    keys
+}
+
+/// Decode Arrow IPC or Parquet bytes, reading only the referenced columns,
+/// giving the schema of the columns read and their batches.
+fn columnar(
+    format: Format,
+    bytes: Vec<u8>,
+    spec: &Value,
+) -> Result<(SchemaRef, Vec<RecordBatch>), Error> {
+    match format {
+        Format::Arrow if bytes.starts_with(b"ARROW1") => {
+            let schema = FileReader::try_new(Cursor::new(&bytes), None)?.schema();
+            let projection = projection(spec, schema.fields().iter().map(|f| f.name()));
+            let reader = FileReader::try_new(Cursor::new(&bytes), projection)?;
+            batches(reader)
+        }
+        Format::Arrow => {
+            let schema = StreamReader::try_new(Cursor::new(&bytes), None)?.schema();
+            let projection = projection(spec, schema.fields().iter().map(|f| f.name()));
+            let reader = StreamReader::try_new(Cursor::new(&bytes), projection)?;
+            batches(reader)
+        }
+        Format::Parquet => {
+            let builder = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(bytes))?;
+            let fields = builder.schema().fields().clone();
+            let builder = match projection(spec, fields.iter().map(|f| f.name())) {
+                Some(indices) => {
+                    let mask = ProjectionMask::roots(builder.parquet_schema(), indices);
+                    builder.with_projection(mask)
+                }
+                None => builder,
+            };
+            batches(builder.build()?)
+        }
+        _ => Err(Error::NotColumnar(format)),
+    }
+}
+
+fn batches(reader: impl RecordBatchReader) -> Result<(SchemaRef, Vec<RecordBatch>), Error> {
+    let schema = reader.schema();
+    let batches = reader.collect::<Result<Vec<_>, ArrowError>>()?;
+    Ok((schema, batches))
+}
+
+/// The indices of the columns to read, or `None` for all of them.
+fn projection<'a>(spec: &Value, names: impl Iterator<Item = &'a String>) -> Option<Vec<usize>> {
+    let names = names.map(String::as_str).collect::<Vec<_>>();
+    spec::referenced_columns(spec, &names)
+}
+
+/// Parse newline delimited JSON into an array of records, skipping blank lines.
+fn lines(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let records = bytes
+        .split(|b| *b == b'\n')
+        .enumerate()
+        .filter(|(_, line)| !line.trim_ascii().is_empty())
+        .map(|(i, line)| serde_json::from_slice(line).map_err(|e| Error::Line(i + 1, e)))
+        .collect::<Result<Vec<Value>, Error>>()?;
+    Ok(serde_json::to_vec(&records)?)
+}
+
+/// Parse delimited text with a header row into JSON records.
+fn delimited(delimiter: u8, bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(bytes);
+    let headers = reader.headers()?.clone();
+    let rows = reader
+        .records()
+        .collect::<Result<Vec<csv::StringRecord>, csv::Error>>()?;
+    let types = headers
+        .iter()
+        .enumerate()
+        .map(|(i, _)| ColumnType::infer(rows.iter().filter_map(|row| row.get(i))))
+        .collect::<Vec<_>>();
+    let records = rows
+        .iter()
+        .map(|row| {
+            let record = headers
+                .iter()
+                .zip(&types)
+                .zip(row.iter())
+                .map(|((name, kind), cell)| (name.to_string(), kind.convert(cell)))
+                .collect::<Map<String, Value>>();
+            Value::Object(record)
+        })
+        .collect::<Vec<_>>();
+    Ok(serde_json::to_vec(&records)?)
+}
+
+/// Run a query against a SQLite database, opened read-only, giving JSON records.
+pub fn query(db: &Path, sql: &str) -> Result<Vec<u8>, Error> {
+    let conn = Connection::open_with_flags(db, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = conn.prepare(sql)?;
+    let columns = stmt
+        .columns()
+        .iter()
+        .map(|c| {
+            (
+                c.name().to_string(),
+                c.decl_type().map(ColumnType::declared),
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut rows = stmt.query([])?;
+    let mut records = Vec::new();
+    while let Some(row) = rows.next()? {
+        let mut record = Map::new();
+        for (i, (name, kind)) in columns.iter().enumerate() {
+            record.insert(name.clone(), sql_value(*kind, row.get_ref(i)?));
+        }
+        records.push(Value::Object(record));
+    }
+    Ok(serde_json::to_vec(&records)?)
+}
+
+/// The names of the columns a query gives, without running it.
+pub fn query_columns(db: &Path, sql: &str) -> Result<Vec<String>, Error> {
+    let conn = Connection::open_with_flags(db, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let stmt = conn.prepare(sql)?;
+    Ok(stmt.column_names().into_iter().map(String::from).collect())
+}
+
+/// The JSON value of a SQLite value in a column of the given declared type.
+/// Expression columns have no declared type and keep their storage class.
+fn sql_value(kind: Option<ColumnType>, value: ValueRef) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(i) if kind == Some(ColumnType::Boolean) => Value::Bool(i != 0),
+        ValueRef::Integer(i) => Value::from(i),
+        ValueRef::Real(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+        ValueRef::Text(t) => {
+            let text = String::from_utf8_lossy(t);
+            match kind {
+                Some(kind) => kind.convert(&text),
+                None => Value::String(text.into_owned()),
+            }
+        }
+        ValueRef::Blob(b) => Value::String(b.iter().map(|x| format!("{x:02x}")).collect()),
+    }
+}
+
+/// The type of a column of delimited text or a database table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Number,
+    Boolean,
+    Date,
+    Text,
+}
+
+impl ColumnType {
+    /// The narrowest type that fits every non-empty cell of delimited text.
+    fn infer<'a>(cells: impl Iterator<Item = &'a str>) -> Self {
+        let mut candidates = [Self::Number, Self::Boolean, Self::Date].to_vec();
+        let mut empty = true;
+        for cell in cells.map(str::trim).filter(|c| !c.is_empty()) {
+            empty = false;
+            candidates.retain(|kind| kind.parse(cell).is_some());
+            if candidates.is_empty() {
+                return Self::Text;
+            }
+        }
+        if empty {
+            Self::Text
+        } else {
+            candidates[0]
+        }
+    }
+
+    /// The type of a SQLite column, following the affinity rules for its declared
+    /// type but recognizing the conventional boolean and date declarations.
+    fn declared(decl: &str) -> Self {
+        let decl = decl.to_ascii_uppercase();
+        if decl.contains("BOOL") {
+            Self::Boolean
+        } else if decl.contains("DATE") || decl.contains("TIME") {
+            Self::Date
+        } else if decl.contains("INT") {
+            Self::Number
+        } else if ["CHAR", "CLOB", "TEXT", "BLOB"]
+            .iter()
+            .any(|t| decl.contains(t))
+            || decl.is_empty()
+        {
+            Self::Text
+        } else {
+            // REAL and NUMERIC affinity
+            Self::Number
+        }
+    }
+
+    /// The JSON value of a cell in a column of this type.
+    fn convert(self, cell: &str) -> Value {
+        let trimmed = cell.trim();
+        if trimmed.is_empty() {
+            Value::Null
+        } else {
+            self.parse(trimmed)
+                .unwrap_or_else(|| Value::String(cell.to_string()))
+        }
+    }
+
+    fn parse(self, cell: &str) -> Option<Value> {
+        match self {
+            Self::Number => parse_number(cell).map(Value::Number),
+            Self::Boolean => parse_boolean(cell).map(Value::Bool),
+            Self::Date => parse_date(cell).map(Value::String),
+            Self::Text => Some(Value::String(cell.to_string())),
+        }
+    }
+}
+
+/// A number, unless the cell is an integer that a float can't hold exactly,
+/// or has leading zeros, as ids and zip codes do.
+fn parse_number(cell: &str) -> Option<Number> {
+    const EXACT: u64 = 1 << f64::MANTISSA_DIGITS;
+    let digits = cell.strip_prefix(['-', '+']).unwrap_or(cell);
+    if digits.len() > 1 && digits.starts_with('0') && digits.as_bytes()[1].is_ascii_digit() {
+        None
+    } else if digits.bytes().all(|b| b.is_ascii_digit()) {
+        cell.parse::<i64>()
+            .ok()
+            .filter(|n| n.unsigned_abs() <= EXACT)
+            .map(Number::from)
+    } else {
+        cell.parse::<f64>().ok().and_then(Number::from_f64)
+    }
+}
+
+fn parse_boolean(cell: &str) -> Option<bool> {
+    if cell.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if cell.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Recognize common date and time layouts, normalized to ISO 8601.
+pub fn parse_date(cell: &str) -> Option<String> {
+    const DATE_TIMES: &[&str] = &[
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ];
+    const DATES: &[&str] = &["%Y-%m-%d", "%Y/%m/%d"];
+
+    if let Ok(t) = DateTime::parse_from_rfc3339(cell) {
+        return Some(t.to_rfc3339());
+    }
+    if let Some(t) = DATE_TIMES
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(cell, f).ok())
+    {
+        return Some(t.format("%Y-%m-%dT%H:%M:%S%.f").to_string());
+    }
+    DATES
+        .iter()
+        .find_map(|f| NaiveDate::parse_from_str(cell, f).ok())
+        .map(|d| d.format("%Y-%m-%d").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::{
+        datatypes::{DataType, Field, Schema},
+        ipc::writer::StreamWriter,
+    };
+    use parquet::arrow::ArrowWriter;
+    use serde_json::json;
+    use std::{env, fs, process, sync::Arc};
+
+    fn records(bytes: Result<Vec<u8>, Error>) -> Value {
+        serde_json::from_slice(&bytes.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn infer() {
+        let column = |cells: &[&str]| ColumnType::infer(cells.iter().copied());
+        assert_eq!(column(&["1", "-2", " 3 "]), ColumnType::Number);
+        assert_eq!(column(&["1.5", "2", "1e3"]), ColumnType::Number);
+        assert_eq!(column(&["true", "FALSE"]), ColumnType::Boolean);
+        assert_eq!(column(&["2024-06-01", "2024/06/02"]), ColumnType::Date);
+        assert_eq!(
+            column(&["2024-06-01T10:00:00Z", "2024-06-01 10:00"]),
+            ColumnType::Date
+        );
+        assert_eq!(column(&["1", "x"]), ColumnType::Text);
+        assert_eq!(column(&["1", "true"]), ColumnType::Text);
+        assert_eq!(column(&["", "2", " "]), ColumnType::Number);
+        assert_eq!(column(&["", ""]), ColumnType::Text);
+        assert_eq!(column(&[]), ColumnType::Text);
+        assert_eq!(
+            column(&["0", "-0.5", "0e1", "9007199254740992"]),
+            ColumnType::Number
+        );
+        assert_eq!(column(&["123", "00123"]), ColumnType::Text);
+        assert_eq!(column(&["-007"]), ColumnType::Text);
+        assert_eq!(column(&["1", "9007199254740993"]), ColumnType::Text);
+        assert_eq!(column(&["12345678901234567890"]), ColumnType::Text);
+        assert_eq!(
+            ColumnType::Number.convert("12345678901234567890"),
+            Value::String("12345678901234567890".to_string())
+        );
+    }
+
+    #[test]
+    fn csv() {
+        let csv = "make,price,used,sold,note\nford,9.5,true,2024-06-01,\nfiat,10,false,,x\n";
+        assert_eq!(
+            records(to_json(Format::Csv, csv.into(), &Value::Null)),
+            json!([
+                { "make": "ford", "price": 9.5, "used": true, "sold": "2024-06-01", "note": null },
+                { "make": "fiat", "price": 10, "used": false, "sold": null, "note": "x" },
+            ])
+        );
+    }
+
+    #[test]
+    fn tsv() {
+        let tsv = "make\tprice\nford, inc\t1\nfiat\tn/a\n";
+        assert_eq!(
+            records(to_json(Format::Tsv, tsv.into(), &Value::Null)),
+            json!([
+                { "make": "ford, inc", "price": "1" },
+                { "make": "fiat", "price": "n/a" },
+            ])
+        );
+    }
+
+    #[test]
+    fn ndjson() {
+        let ndjson = "{\"a\": 1}\n\n  \n{\"a\": 2}\n";
+        assert_eq!(
+            records(to_json(Format::Ndjson, ndjson.into(), &Value::Null)),
+            json!([{ "a": 1 }, { "a": 2 }])
+        );
+        let bad = "{\"a\": 1}\n\n{\"a\": }\n";
+        let error = to_json(Format::Ndjson, bad.into(), &Value::Null).unwrap_err();
+        assert!(matches!(error, Error::Line(3, _)), "{error}");
+        assert!(error
+            .to_string()
+            .starts_with("invalid JSON record on line 3: "));
+    }
+
+    #[test]
+    fn empty_arrow() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int64, true),
+            Field::new("b", DataType::Utf8, true),
+        ]));
+        let mut writer = StreamWriter::try_new(Vec::new(), &schema).unwrap();
+        writer.finish().unwrap();
+        let bytes = writer.into_inner().unwrap();
+        let spec = json!({ "encoding": { "x": { "field": "a" } } });
+
+        let arrow = to_arrow(Format::Arrow, bytes.clone(), &spec).unwrap();
+        let reader = FileReader::try_new(Cursor::new(arrow), None).unwrap();
+        assert_eq!(reader.schema().fields().len(), 1);
+        assert_eq!(reader.num_batches(), 0);
+        assert_eq!(to_json(Format::Arrow, bytes, &spec).unwrap(), b"[]");
+    }
+
+    #[test]
+    fn declared() {
+        let types = [
+            ("INTEGER", ColumnType::Number),
+            ("DECIMAL(10,2)", ColumnType::Number),
+            ("real", ColumnType::Number),
+            ("BOOLEAN", ColumnType::Boolean),
+            ("DATETIME", ColumnType::Date),
+            ("timestamp", ColumnType::Date),
+            ("VARCHAR(20)", ColumnType::Text),
+            ("BLOB", ColumnType::Text),
+            ("", ColumnType::Text),
+        ];
+        for (decl, kind) in types {
+            assert_eq!(ColumnType::declared(decl), kind, "{decl}");
+        }
+    }
+
+    #[test]
+    fn sqlite() {
+        let db = env::temp_dir().join(format!("vega-view-test-{}.db", process::id()));
+        let _ = fs::remove_file(&db);
+        Connection::open(&db)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE cars (make TEXT, price REAL, doors INTEGER, used BOOLEAN, sold DATE);
+                 INSERT INTO cars VALUES ('ford', 9.5, 4, 1, '2024-06-01');
+                 INSERT INTO cars VALUES ('fiat', NULL, NULL, 0, NULL);",
+            )
+            .unwrap();
+        let rows = query(&db, "SELECT *, doors * 2 AS twice FROM cars ORDER BY make");
+        let error = query(&db, "SELECT nothing FROM cars");
+        let columns = query_columns(&db, "SELECT make, doors * 2 AS twice FROM cars");
+        fs::remove_file(&db).unwrap();
+
+        let rows: Value = serde_json::from_slice(&rows.unwrap()).unwrap();
+        assert_eq!(
+            rows,
+            json!([
+                { "make": "fiat", "price": null, "doors": null, "used": false, "sold": null, "twice": null },
+                { "make": "ford", "price": 9.5, "doors": 4, "used": true, "sold": "2024-06-01", "twice": 8 },
+            ])
+        );
+        assert!(matches!(error, Err(Error::Sqlite(_))), "{error:?}");
+        assert_eq!(columns.unwrap(), ["make", "twice"]);
+    }
+
+    #[test]
+    fn columns() {
+        let names = |format, bytes: &[u8]| super::columns(format, bytes).unwrap();
+        assert_eq!(
+            names(Format::Csv, b"make,price\nford,1\n"),
+            ["make", "price"]
+        );
+        assert_eq!(names(Format::Tsv, b"make\tprice\n"), ["make", "price"]);
+        assert_eq!(
+            names(Format::Json, br#"[{"a": 1}, {"b": 2, "a": 3}]"#),
+            ["a", "b"]
+        );
+        assert_eq!(
+            names(Format::Ndjson, b"{\"a\": 1}\n{\"b\": 2}\n"),
+            ["a", "b"]
+        );
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int64, true),
+            Field::new("b", DataType::Utf8, true),
+        ]));
+        let mut writer = StreamWriter::try_new(Vec::new(), &schema).unwrap();
+        writer.finish().unwrap();
+        let bytes = writer.into_inner().unwrap();
+        assert_eq!(names(Format::Arrow, &bytes), ["a", "b"]);
+        let file = to_arrow(Format::Arrow, bytes, &Value::Null).unwrap();
+        assert_eq!(names(Format::Arrow, &file), ["a", "b"]);
+        let mut writer = ArrowWriter::try_new(Vec::new(), schema, None).unwrap();
+        writer.flush().unwrap();
+        let parquet = writer.into_inner().unwrap();
+        assert_eq!(names(Format::Parquet, &parquet), ["a", "b"]);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::view::is_route;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::Value;
+use std::{fs, io, path::Path};
+
+/// A self-contained page with the script and the specification, its data inlined,
+/// in place of the `/script` and `/spec` urls of a page template.
+/// The embed options are given as a data url in place of `/options`.
+/// It is an error for the page to load anything else from vega-view.
+pub fn html(page: &str, script: &str, spec: &Value, options: &Value) -> io::Result<String> {
+    let spec = escape(&spec.to_string());
+    let options = format!(
+        "'data:application/json;base64,{}'",
+        STANDARD.encode(options.to_string())
+    );
+    let page = [
+        ("'/spec'", &spec),
+        ("\"/spec\"", &spec),
+        ("'/options'", &options),
+        ("\"/options\"", &options),
+    ]
+    .iter()
+    .fold(page.to_string(), |page, (url, inline)| {
+        page.replace(url, inline)
+    });
+    let script = format!(
+        "<script type=\"text/javascript\">\n{}\n</script>",
+        escape(script)
+    );
+    let page = replace_element(&page, "/script", &script);
+    match unreplaced(&page) {
+        Some(url) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("the page loads '{url}' from vega-view, which cannot be inlined"),
+        )),
+        None => Ok(page),
+    }
+}
+
+/// The first quoted string of a page that is the url of a route of vega-view.
+fn unreplaced(page: &str) -> Option<&str> {
+    let mut rest = page;
+    while let Some(i) = rest.find(['\'', '"', '`']) {
+        let quote = &rest[i..i + 1];
+        rest = &rest[i + 1..];
+        if let Some(url) = rest.find(quote).map(|end| &rest[..end]) {
+            if is_route(url) {
+                return Some(url);
+            }
+        }
+    }
+    None
+}
+
+/// Replace the script element that loads a url with inline content.
+fn replace_element(page: &str, url: &str, content: &str) -> String {
+    let found = ["'", "\""].iter().find_map(|q| {
+        let attr = page.find(&format!("src={q}{url}{q}"))?;
+        let start = page[..attr].rfind("<script")?;
+        let end = attr + page[attr..].find("</script>")? + "</script>".len();
+        Some((start, end))
+    });
+    match found {
+        Some((start, end)) => format!("{}{content}{}", &page[..start], &page[end..]),
+        None => page.to_string(),
+    }
+}
+
+/// Prevent inline text from closing the script element that contains it.
+fn escape(text: &str) -> String {
+    text.replace("</script", "<\\/script")
+}
+
+/// The kinds of image file a view can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Image {
+    Svg,
+    Png,
+}
+
+impl Image {
+    /// The kind of image implied by the extension of a file, if any.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "svg" => Some(Self::Svg),
+            "png" => Some(Self::Png),
+            _ => None,
+        }
+    }
+
+    /// The script that asks the page to render the view as this kind of image.
+    pub fn script(self) -> &'static str {
+        match self {
+            Self::Svg => "vegaExport('svg')",
+            Self::Png => "vegaExport('png')",
+        }
+    }
+
+    /// Write an image rendered by the page, which is SVG text or a PNG data url.
+    pub fn write(self, path: &Path, data: &str) -> io::Result<()> {
+        match self {
+            Self::Svg => fs::write(path, data),
+            Self::Png => {
+                let encoded = data
+                    .strip_prefix("data:image/png;base64,")
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a png"))?;
+                let bytes = STANDARD
+                    .decode(encoded)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                fs::write(path, bytes)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::view::PAGE;
+    use serde_json::json;
+
+    #[test]
+    fn default_page() {
+        let page = String::from_utf8_lossy(PAGE);
+        let spec = json!({"title": "</script>", "data": {"values": [{"a": 1}]}});
+        let options = json!({"theme": "dark"});
+        let inlined = html(&page, "vegaEmbed = () => '</script>';", &spec, &options).unwrap();
+        assert!(!inlined.contains("src='/script'"));
+        assert!(inlined.contains(
+            "<script type=\"text/javascript\">\nvegaEmbed = () => '<\\/script>';\n</script>"
+        ));
+        assert!(inlined.contains(r#"vegaEmbed('#vis', {"title":"<\/script>","data":"#));
+        let options = format!(
+            "fetch('data:application/json;base64,{}')",
+            STANDARD.encode(options.to_string())
+        );
+        assert!(inlined.contains(&options));
+        assert_eq!(unreplaced(&inlined), None);
+    }
+
+    #[test]
+    fn custom_page() {
+        let page = r#"<script src="/script" defer></script><p>"/spec" '/specs'</p>"#;
+        let inlined = html(page, "script", &json!({}), &json!({})).unwrap();
+        assert_eq!(
+            inlined,
+            "<script type=\"text/javascript\">\nscript\n</script><p>{} '/specs'</p>"
+        );
+        let page = r#"<script src="/script"></script><img src="/data/logo.png">"#;
+        let error = html(page, "", &json!({}), &json!({})).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the page loads '/data/logo.png' from vega-view, which cannot be inlined"
+        );
+        let page = "<script src=`/script`></script>";
+        assert!(html(page, "", &json!({}), &json!({})).is_err());
+    }
+
+    #[test]
+    fn elements() {
+        let page = "<head><script async src='/script'></script></head>";
+        assert_eq!(
+            replace_element(page, "/script", "<i/>"),
+            "<head><i/></head>"
+        );
+        assert_eq!(replace_element(page, "/other", "<i/>"), page);
+        assert_eq!(escape("a</script>b</style>"), "a<\\/script>b</style>");
+    }
+}
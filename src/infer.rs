@@ -0,0 +1,213 @@
+use crate::data;
+use serde_json::{json, Map, Value};
+
+/// How a column of the data would be encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Quantitative,
+    Temporal,
+    Nominal,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Quantitative => "quantitative",
+            Self::Temporal => "temporal",
+            Self::Nominal => "nominal",
+        }
+    }
+
+    /// The kind of a column given its values, ignoring nulls.
+    fn of<'a>(values: impl Iterator<Item = &'a Value>) -> Option<Self> {
+        let mut kind = None;
+        for value in values {
+            let this = match value {
+                Value::Null => continue,
+                Value::Number(_) => Self::Quantitative,
+                Value::String(s) if data::parse_date(s).is_some() => Self::Temporal,
+                Value::String(_) | Value::Bool(_) => Self::Nominal,
+                Value::Array(_) | Value::Object(_) => return None,
+            };
+            kind = match kind {
+                None => Some(this),
+                Some(k) if k == this => Some(k),
+                Some(_) => Some(Self::Nominal),
+            };
+        }
+        kind
+    }
+}
+
+/// A vega-lite specification suited to the columns of the data:
+/// a line chart for a temporal and a numeric column,
+/// a bar chart for a nominal and a numeric column,
+/// a scatter plot for two numeric columns,
+/// or a histogram or bar chart of counts for a single column.
+/// Any further nominal column is used for colour.
+pub fn spec(records: &[Value]) -> Result<Value, String> {
+    let mut names = Vec::<&String>::new();
+    for record in records.iter().filter_map(Value::as_object) {
+        names.extend(
+            record
+                .keys()
+                .filter(|k| !names.contains(k))
+                .collect::<Vec<_>>(),
+        );
+    }
+    let columns = names
+        .into_iter()
+        .filter_map(|name| {
+            let kind = Kind::of(records.iter().filter_map(|r| r.get(name)))?;
+            Some((name.as_str(), kind))
+        })
+        .collect::<Vec<_>>();
+    let first = |kind: Kind, skip: usize| {
+        columns
+            .iter()
+            .filter(|(_, k)| *k == kind)
+            .nth(skip)
+            .map(|(name, _)| *name)
+    };
+    let temporal = first(Kind::Temporal, 0);
+    let (x, y) = (first(Kind::Quantitative, 0), first(Kind::Quantitative, 1));
+    let nominal = first(Kind::Nominal, 0);
+
+    let (mark, mut encoding) = match (temporal, x, y, nominal) {
+        (Some(time), Some(value), _, _) => (
+            "line",
+            json!({
+                "x": channel(time, Kind::Temporal),
+                "y": channel(value, Kind::Quantitative),
+            }),
+        ),
+        (None, Some(x), Some(y), _) => (
+            "point",
+            json!({
+                "x": channel(x, Kind::Quantitative),
+                "y": channel(y, Kind::Quantitative),
+            }),
+        ),
+        (None, Some(value), None, Some(category)) => {
+            let mut y = channel(value, Kind::Quantitative);
+            y.insert("aggregate".to_string(), json!("sum"));
+            (
+                "bar",
+                json!({ "x": channel(category, Kind::Nominal), "y": y }),
+            )
+        }
+        (None, Some(value), None, None) => {
+            let mut x = channel(value, Kind::Quantitative);
+            x.insert("bin".to_string(), json!(true));
+            ("bar", json!({ "x": x, "y": { "aggregate": "count" } }))
+        }
+        (Some(time), None, _, _) => {
+            let mut x = channel(time, Kind::Temporal);
+            x.insert("timeUnit".to_string(), json!("yearmonthdate"));
+            ("bar", json!({ "x": x, "y": { "aggregate": "count" } }))
+        }
+        (None, None, _, Some(category)) => (
+            "bar",
+            json!({ "x": channel(category, Kind::Nominal), "y": { "aggregate": "count" } }),
+        ),
+        (None, None, _, None) => {
+            return Err("unable to infer a spec, the data has no usable columns".to_string())
+        }
+    };
+
+    // colour by the first nominal column not already on an axis
+    let used = encoding
+        .as_object()
+        .into_iter()
+        .flat_map(|e| e.values())
+        .filter_map(|c| c.get("field").and_then(Value::as_str))
+        .map(String::from)
+        .collect::<Vec<_>>();
+    let colour = columns
+        .iter()
+        .find(|(name, kind)| *kind == Kind::Nominal && !used.iter().any(|u| u == name));
+    if let (Some((name, _)), Some(encoding)) = (colour, encoding.as_object_mut()) {
+        encoding.insert(
+            "color".to_string(),
+            Value::Object(channel(name, Kind::Nominal)),
+        );
+    }
+
+    Ok(json!({
+        "$schema": "https://vega.github.io/schema/vega-lite/v5.json",
+        "data": { "url": "/data" },
+        "mark": { "type": mark, "tooltip": true },
+        "width": "container",
+        "encoding": encoding,
+    }))
+}
+
+fn channel(field: &str, kind: Kind) -> Map<String, Value> {
+    let mut channel = Map::new();
+    channel.insert("field".to_string(), json!(field));
+    channel.insert("type".to_string(), json!(kind.name()));
+    channel
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The mark and encoding inferred for the records.
+    fn inferred(records: Value) -> (Value, Value) {
+        let spec = spec(records.as_array().unwrap()).unwrap();
+        (spec["mark"]["type"].clone(), spec["encoding"].clone())
+    }
+
+    #[test]
+    fn line() {
+        let (mark, encoding) = inferred(json!([
+            { "date": "2024-06-01", "value": 1, "site": "a" },
+            { "date": "2024-06-02", "value": null, "site": "b" },
+        ]));
+        assert_eq!(mark, "line");
+        assert_eq!(
+            encoding,
+            json!({
+                "x": { "field": "date", "type": "temporal" },
+                "y": { "field": "value", "type": "quantitative" },
+                "color": { "field": "site", "type": "nominal" },
+            })
+        );
+    }
+
+    #[test]
+    fn scatter() {
+        let (mark, encoding) = inferred(json!([{ "a": 1, "b": 2.5 }, { "a": 2 }]));
+        assert_eq!(mark, "point");
+        assert_eq!(encoding["x"]["field"], "a");
+        assert_eq!(encoding["y"]["field"], "b");
+    }
+
+    #[test]
+    fn bars() {
+        let (mark, encoding) = inferred(json!([{ "make": "ford", "price": 1 }]));
+        assert_eq!(mark, "bar");
+        assert_eq!(encoding["x"]["field"], "make");
+        assert_eq!(encoding["y"]["aggregate"], "sum");
+
+        let (mark, encoding) = inferred(json!([{ "price": 1 }, { "price": 2 }]));
+        assert_eq!(mark, "bar");
+        assert_eq!(encoding["x"]["bin"], true);
+        assert_eq!(encoding["y"], json!({ "aggregate": "count" }));
+
+        // a column of numbers and text is nominal
+        let (_, encoding) = inferred(json!([{ "code": 1 }, { "code": "x" }]));
+        assert_eq!(encoding["x"], json!({ "field": "code", "type": "nominal" }));
+        assert_eq!(encoding["y"], json!({ "aggregate": "count" }));
+    }
+
+    #[test]
+    fn unusable() {
+        let error = spec(&[json!({ "nested": { "a": 1 } }), json!(3)]).unwrap_err();
+        assert_eq!(
+            error,
+            "unable to infer a spec, the data has no usable columns"
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use crate::select;
+use serde::Deserialize;
+
+/// A message posted by the page with `window.ipc.postMessage`.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Message {
+    /// The visualization has been embedded in the page.
+    Ready,
+    /// The view rendered as an image, SVG text or a data url.
+    Image { data: String },
+    /// The visualization could not be parsed, embedded or rendered,
+    /// or a script in the page failed.
+    Error { message: String },
+    /// A warning from vega or vega-lite.
+    Warning { message: String },
+    /// The store of a selection parameter has changed.
+    Selection {
+        name: String,
+        store: Vec<select::Tuple>,
+    },
+    /// The user has pressed Enter to finish.
+    Submit,
+}
+
+impl Message {
+    /// Parse the JSON body of a message.
+    pub fn parse(body: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(body)
+    }
+}
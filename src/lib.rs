@@ -0,0 +1,38 @@
+//! Display Vega and Vega-Lite visualizations in a webview.
+//!
+//! A [`ViewBuilder`] gathers the spec, the data and the window settings,
+//! then [`ViewBuilder::run`] shows the view on the main thread
+//! or [`ViewBuilder::spawn`] shows it from a new thread.
+//! [`run_all`] shows several views at once, each in its own window.
+//! On Unix, [`ViewBuilder::send`] shows it in a long-lived process started
+//! with [`server::serve`] instead.
+//! Specs can be written as JSON or built with the types of [`vegalite`].
+
+pub mod chart;
+pub mod data;
+mod export;
+mod infer;
+mod ipc;
+mod lint;
+mod pages;
+pub mod schema;
+mod select;
+#[cfg(unix)]
+pub mod server;
+pub mod spec;
+mod stream;
+pub mod template;
+pub mod theme;
+pub mod vegalite;
+mod view;
+mod watch;
+mod window;
+
+pub use data::Format;
+pub use export::Image;
+pub use pages::run_all;
+pub use spec::{Mode, Patch};
+pub use template::Var;
+pub use theme::Theme;
+pub use view::{Problem, ViewBuilder};
+pub use window::{Outcome, RENDER_ERROR};
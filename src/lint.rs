@@ -0,0 +1,232 @@
+use serde_json::Value;
+use std::{collections::BTreeSet, fmt};
+
+/// A field referred to by the specification that is not in the data.
+#[derive(Debug, Clone)]
+pub struct UnknownField {
+    pub field: String,
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for UnknownField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the field '{}' is not in the data", self.field)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, ", did you mean '{suggestion}'?")?;
+        }
+        Ok(())
+    }
+}
+
+/// The fields used by encodings and transforms that are neither columns
+/// of the data served at `/data` nor derived by a transform.
+pub fn unknown_fields(spec: &Value, columns: &[String]) -> Vec<UnknownField> {
+    let columns = columns.iter().cloned().collect::<BTreeSet<_>>();
+    let mut refs = Refs::default();
+    refs.visit(spec, true);
+    if refs.opaque {
+        return Vec::new();
+    }
+    refs.used
+        .into_iter()
+        .filter(|field| {
+            let root = field.split('.').next().unwrap_or(field);
+            !columns.contains(field) && !columns.contains(root) && !refs.derived.contains(field)
+        })
+        .map(|field| UnknownField {
+            suggestion: suggest(&field, &columns),
+            field,
+        })
+        .collect()
+}
+
+/// The closest column name to a misspelled field.
+fn suggest(field: &str, columns: &BTreeSet<String>) -> Option<String> {
+    columns
+        .iter()
+        .map(|c| {
+            (
+                strsim::jaro_winkler(&field.to_lowercase(), &c.to_lowercase()),
+                c,
+            )
+        })
+        .filter(|(score, _)| *score >= 0.8)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, c)| c.clone())
+}
+
+/// The fields used and derived in a specification.
+#[derive(Debug, Default)]
+struct Refs {
+    used: BTreeSet<String>,
+    derived: BTreeSet<String>,
+    /// A transform derives fields that can't be known without the data.
+    opaque: bool,
+}
+
+impl Refs {
+    /// Visit a view, which is checked if it uses `/data`, either as its own data
+    /// or inherited from the view it is in.
+    fn visit(&mut self, spec: &Value, checked: bool) {
+        match spec {
+            Value::Object(map) => {
+                let checked = match map.get("data") {
+                    Some(data) => data.get("url").and_then(Value::as_str) == Some("/data"),
+                    None => checked,
+                };
+                for (key, value) in map {
+                    match key.as_str() {
+                        "encoding" if checked => self.encoding(value),
+                        "transform" => self.transforms(value, checked),
+                        _ => self.visit(value, checked),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter().for_each(|v| self.visit(v, checked)),
+            _ => {}
+        }
+    }
+
+    fn encoding(&mut self, value: &Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    match (key.as_str(), value) {
+                        ("field", Value::String(field)) => {
+                            self.used.insert(field.clone());
+                        }
+                        _ => self.encoding(value),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter().for_each(|v| self.encoding(v)),
+            _ => {}
+        }
+    }
+
+    fn transforms(&mut self, value: &Value, checked: bool) {
+        match value {
+            Value::Object(map) => {
+                if map.contains_key("pivot") {
+                    self.opaque = true;
+                }
+                for (key, value) in map {
+                    match key.as_str() {
+                        "as" => self.derived.extend(strings(value)),
+                        "field" | "groupby" | "fields" | "lookup" if checked => {
+                            self.used.extend(strings(value))
+                        }
+                        // the secondary data of a lookup, and the fields taken from it
+                        "from" if !map.contains_key("as") => self
+                            .derived
+                            .extend(value.get("fields").map(strings).unwrap_or_default()),
+                        "from" => {}
+                        _ => self.transforms(value, checked),
+                    }
+                }
+                for (transform, outputs) in [
+                    ("fold", ["key", "value"]),
+                    ("density", ["value", "density"]),
+                    ("quantile", ["prob", "value"]),
+                ] {
+                    if map.contains_key(transform) && !map.contains_key("as") {
+                        self.derived.extend(outputs.map(String::from));
+                    }
+                }
+            }
+            Value::Array(items) => items.iter().for_each(|v| self.transforms(v, checked)),
+            _ => {}
+        }
+    }
+}
+
+/// A string or the strings in an array.
+fn strings(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(s) => vec![s.clone()],
+        Value::Array(items) => items.iter().flat_map(strings).collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn unknown(spec: &Value) -> Vec<String> {
+        let columns = ["date", "value", "site"].map(String::from);
+        unknown_fields(spec, &columns)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn fields() {
+        let spec = json!({
+            "data": { "url": "/data" },
+            "mark": "line",
+            "encoding": {
+                "x": { "field": "date", "type": "temporal" },
+                "y": { "field": "vlaue", "type": "quantitative" },
+                "color": { "field": "site.name" },
+                "tooltip": [{ "field": "total" }, { "field": "region" }],
+            },
+            "transform": [
+                { "calculate": "datum.value * 2", "as": "total" },
+                { "fold": ["value"] },
+            ],
+        });
+        assert_eq!(
+            unknown(&spec),
+            [
+                "the field 'region' is not in the data",
+                "the field 'vlaue' is not in the data, did you mean 'value'?",
+            ]
+        );
+    }
+
+    #[test]
+    fn own_data() {
+        let spec = json!({
+            "layer": [
+                { "data": { "url": "other.csv" }, "encoding": { "x": { "field": "other" } } },
+                { "encoding": { "x": { "field": "date" }, "y": { "field": "key" } } },
+            ],
+        });
+        assert_eq!(unknown(&spec), ["the field 'key' is not in the data"]);
+        let pivot = json!({
+            "transform": [{ "pivot": "site", "value": "value" }],
+            "encoding": { "x": { "field": "anything" } },
+        });
+        assert!(unknown(&pivot).is_empty());
+    }
+
+    #[test]
+    fn inherited_data() {
+        let spec = json!({
+            "data": { "url": "/data/sites" },
+            "layer": [
+                { "mark": "point", "encoding": { "x": { "field": "region" } } },
+                {
+                    "transform": [{ "filter": "datum.open", "as": "open" }],
+                    "layer": [{ "encoding": { "y": { "field": "site_id" } } }],
+                },
+                { "data": { "url": "/data" }, "encoding": { "x": { "field": "vlaue" } } },
+            ],
+        });
+        assert_eq!(
+            unknown(&spec),
+            ["the field 'vlaue' is not in the data, did you mean 'value'?"]
+        );
+    }
+
+    #[test]
+    fn suggestions() {
+        let columns = ["price", "Make", "model"].map(String::from).into();
+        assert_eq!(suggest("prce", &columns).as_deref(), Some("price"));
+        assert_eq!(suggest("make", &columns).as_deref(), Some("Make"));
+        assert_eq!(suggest("weight", &columns), None);
+    }
+}
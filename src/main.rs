@@ -1,33 +1,53 @@
-use clap::Parser;
-use std::{
-    borrow::Cow,
-    fmt::Debug,
-    fs::File,
-    io::{stdin, Read},
-    path::{Path, PathBuf},
-    time::Instant,
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser};
+use std::path::{Path, PathBuf};
+use vega_view::{
+    chart::{self, Chart},
+    data::Dataset,
+    Format, Image, Mode, Patch, Problem, Theme, Var, ViewBuilder,
 };
-use tao::{
-    dpi::PhysicalSize,
-    event::{Event, StartCause, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
-};
-use wry::{
-    http::{Method, Request, Response, StatusCode},
-    WebViewBuilder,
-};
-
-const SCHEME: &str = "view";
-const BASE: &str = "view://local/page";
-const PAGE: &[u8] = include_bytes!("vega-page.html");
-const SCRIPT: &[u8] = include_bytes!("vega-all.js");
 
 /// Display a Web View, usually for Vega visualizations.
 #[derive(Parser, Clone, Debug)]
 struct Args {
-    /// A vega-lite specification for this visualization.
-    spec: String,
+    /// A vega-lite specification for this visualization, or a file containing it
+    /// (default is inferred from the columns of the data).
+    spec: Option<String>,
+
+    /// A preset chart to use as the spec.
+    #[command(subcommand)]
+    chart: Option<Chart>,
+
+    /// Swap the x and y axes of the preset chart.
+    #[arg(long, global = true)]
+    flip: bool,
+
+    /// A JSON, YAML or JSON5 file containing the vega-lite specification,
+    /// or - for stdin when the data comes from a file.
+    #[arg(long, conflicts_with = "spec")]
+    spec_file: Option<PathBuf>,
+
+    /// A value for the {{name}} placeholders in the spec, given as name=value.
+    /// {{env.NAME}} placeholders take environment variables. (repeatable)
+    #[arg(long)]
+    var: Vec<Var>,
+
+    /// Set a property of the spec, given as path=value where the path is dotted
+    /// or a JSON pointer and the value is JSON or else a string. (repeatable)
+    #[arg(long)]
+    set: Vec<Patch>,
+
+    /// The language of the spec (default is detected from its $schema).
+    #[arg(long, value_enum)]
+    mode: Option<Mode>,
+
+    /// The vega-themes preset for the chart, or auto to follow the desktop (default is light).
+    #[arg(long, value_enum)]
+    theme: Option<Theme>,
+
+    /// A JSON, YAML or JSON5 file of vega config, merged into the spec's config,
+    /// overriding its settings.
+    #[arg(long)]
+    config: Option<PathBuf>,
 
     /// A file containing a HTML template for the page.
     #[arg(long)]
@@ -41,154 +61,348 @@ struct Args {
     #[arg(long)]
     data: Option<PathBuf>,
 
+    /// The format of the data (default is inferred from the data file extension, or json).
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    /// A SQLite database file to query for the data to visualize.
+    #[arg(long, requires = "query", conflicts_with = "data")]
+    sqlite: Option<PathBuf>,
+
+    /// The SQL query giving the data to visualize from the SQLite database.
+    #[arg(long, requires = "sqlite")]
+    query: Option<String>,
+
+    /// A named dataset, given as name=path and served at /data/name.
+    /// Spec entries of the form `data: {name: ...}` are linked to it. (repeatable)
+    #[arg(long)]
+    dataset: Vec<Dataset>,
+
+    /// Stream newline delimited JSON records from stdin into the view as they arrive.
+    /// The spec data is replaced by a dataset named "stream".
+    #[arg(long, conflicts_with_all = ["data", "sqlite"])]
+    stream: bool,
+
+    /// The number of most recent records to show when streaming (default is all).
+    #[arg(long, requires = "stream")]
+    window: Option<usize>,
+
+    /// Serve arrow or parquet data to the page as Arrow IPC rather than JSON.
+    /// Needs a --page or --script that registers vega's arrow loader.
+    #[arg(long)]
+    serve_arrow: bool,
+
+    /// Show a view in a window of its own, given as its spec file, then optionally its data
+    /// file, or "" for none, and title.  The other options apply to every view. (repeatable)
+    #[arg(long, num_args = 1..=3, value_names = ["SPEC", "DATA", "TITLE"], conflicts_with_all = [
+        "spec", "spec_file", "stream", "select", "select_index", "output", "export_html",
+        "print_spec", "watch", "target",
+    ])]
+    view: Vec<String>,
+
     /// The window title.
     #[arg(long)]
     title: Option<String>,
 
     /// The window width.
-    #[arg(long)]
-    width: Option<u32>,
+    #[arg(long, default_value_t = 1000)]
+    width: u32,
 
     /// The window height.
+    #[arg(long, default_value_t = 800)]
+    height: u32,
+
+    /// Render the view to an svg or png file in a hidden window, then exit.
+    /// This needs a display, such as Xvfb on a headless machine.
+    #[arg(long, value_parser = image_path)]
+    output: Option<PathBuf>,
+
+    /// Check the spec against the vega-lite schema, then exit.
+    #[arg(long)]
+    check: bool,
+
+    /// Print the spec, as inferred or with datasets linked, then exit.
+    #[arg(long)]
+    print_spec: bool,
+
+    /// Treat fields in the spec that are not in the data as errors rather than warnings.
+    #[arg(long)]
+    strict: bool,
+
+    /// Write a self-contained HTML page with the script, spec and data inlined, then exit.
+    #[arg(long)]
+    export_html: Option<PathBuf>,
+
+    /// Exit as soon as the page reports an error, with exit status 2.
+    #[arg(long)]
+    exit_on_error: bool,
+
+    /// Print the data rows picked with the chart's selection parameters as JSON,
+    /// when the window is closed or Enter is pressed.
+    #[arg(long, conflicts_with = "stream")]
+    select: bool,
+
+    /// As --select, but print the positions of the picked rows in the data
+    /// as a JSON array.
+    #[arg(long, conflicts_with_all = ["stream", "select"])]
+    select_index: bool,
+
+    /// Reload the view when the spec, page, script or data files change.
+    #[arg(long)]
+    watch: bool,
+
+    /// Run as a server that shows the views of later vega-view commands in its windows,
+    /// listening on a Unix socket.  Those commands return as soon as the server has the view,
+    /// unless it needs its own process to stream, watch, select or export.
+    #[arg(long)]
+    server: bool,
+
+    /// Show the view in the server window of this name, replacing the view there,
+    /// rather than in a new window.
     #[arg(long)]
-    height: Option<u32>,
+    target: Option<String>,
 
     /// Turn on debug logging.
     #[arg(long)]
     debug: bool,
 }
 
-fn main() -> wry::Result<()> {
-    let args = Args::parse();
-    let log = Log::new(args.debug);
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new()
-        .with_title(args.title.as_deref().unwrap_or("Vega View"))
-        .with_inner_size(PhysicalSize::new(
-            args.width.unwrap_or(1000),
-            args.height.unwrap_or(800),
-        ))
-        .with_decorations(true)
-        .build(&event_loop)
-        .unwrap();
-    let _webview = WebViewBuilder::new(&window)
-        .with_custom_protocol(SCHEME.to_string(), move |r| handler(log, &args, r))
-        .with_url(BASE)
-        .with_devtools(true)
-        .build()?;
-
-    event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Wait;
-
-        match event {
-            Event::NewEvents(StartCause::WaitCancelled { .. }) => {}
-            Event::MainEventsCleared => {}
-            Event::RedrawEventsCleared => {}
-            Event::DeviceEvent { .. } => {}
-            Event::WindowEvent {
-                event: WindowEvent::CloseRequested,
-                ..
-            } => {
-                *control_flow = ControlFlow::Exit;
-                log.print(&event)
-            }
-            _ => log.print(&event),
+impl Args {
+    /// The view described by the arguments.
+    fn view(&self) -> ViewBuilder {
+        let mut view = ViewBuilder::new()
+            .size(self.width, self.height)
+            .serve_arrow(self.serve_arrow)
+            .strict(self.strict)
+            .exit_on_error(self.exit_on_error)
+            .select(self.select || self.select_index)
+            .watch(self.watch)
+            .debug(self.debug);
+        if let Some(spec) = &self.spec {
+            view = view.spec(spec);
+        }
+        if let Some(path) = &self.spec_file {
+            view = view.spec_file(path);
+        }
+        view = self.var.iter().cloned().fold(view, ViewBuilder::var);
+        view = self.set.iter().cloned().fold(view, ViewBuilder::set);
+        if let Some(mode) = self.mode {
+            view = view.mode(mode);
+        }
+        if let Some(theme) = self.theme {
+            view = view.theme(theme);
         }
-    });
+        if let Some(path) = &self.config {
+            view = view.config(path);
+        }
+        if let Some(path) = &self.page {
+            view = view.page(path);
+        }
+        if let Some(path) = &self.script {
+            view = view.script(path);
+        }
+        if let Some(path) = &self.data {
+            view = view.data_file(path);
+        } else if self.sqlite.is_none() {
+            view = view.stdin();
+        }
+        if let Some(format) = self.format {
+            view = view.format(format);
+        }
+        if let (Some(db), Some(query)) = (&self.sqlite, &self.query) {
+            view = view.sqlite(db, query);
+        }
+        for dataset in &self.dataset {
+            view = view.dataset(&dataset.name, &dataset.path);
+        }
+        if self.stream {
+            view = view.stream(self.window);
+        }
+        if let Some(title) = &self.title {
+            view = view.title(title);
+        }
+        if let Some(path) = &self.output {
+            view = view.output(path);
+        }
+        if let Some(name) = &self.target {
+            view = view.target(name);
+        }
+        view
+    }
 }
 
-/// Respond to a local http request.
-fn handler(log: Log, args: &Args, request: Request<Vec<u8>>) -> Response<Cow<'static, [u8]>> {
-    log.print(&request);
-    match *request.method() {
-        Method::GET => match request.uri().path() {
-            "/page" => {
-                let body = if let Some(path) = &args.page {
-                    Cow::from(file_contents(path.as_path()))
-                } else {
-                    Cow::from(PAGE)
-                };
-                Response::builder()
-                    .header("Content-Type", "text/html")
-                    .body(body)
-                    .unwrap()
-            }
-            "/script" => {
-                let body = if let Some(path) = &args.script {
-                    Cow::from(file_contents(path.as_path()))
-                } else {
-                    Cow::from(SCRIPT)
-                };
-                Response::builder()
-                    .header("Content-Type", "text/javascript")
-                    .body(body)
-                    .unwrap()
-            }
-            "/spec" => {
-                let body = Cow::from(args.spec.clone().into_bytes());
-                Response::builder()
-                    .header("Content-Type", "application/json")
-                    .body(body)
-                    .unwrap()
-            }
-            "/data" => {
-                let body = if let Some(path) = &args.data {
-                    Cow::from(file_contents(path.as_path()))
-                } else {
-                    Cow::from(all_input())
-                };
-                log.print(format!("Data Length {}", body.len()));
-                Response::builder()
-                    .header("Content-Type", "application/json")
-                    .body(body)
-                    .unwrap()
-            }
-            _ => Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .body(Cow::from("Not found".as_bytes()))
-                .unwrap(),
-        },
-        _ => Response::builder()
-            .status(StatusCode::METHOD_NOT_ALLOWED)
-            .body(Cow::from("Wrong method".as_bytes()))
-            .unwrap(),
-    }
+/// A view given with --view.
+struct ViewFiles {
+    spec: PathBuf,
+    data: Option<PathBuf>,
+    title: Option<String>,
 }
 
-/// All the bytes from stdin.
-fn all_input() -> Vec<u8> {
-    let mut buf = Vec::<u8>::new();
-    let _n = stdin().read_to_end(&mut buf).expect("unable to read stdin");
-    buf
+impl ViewFiles {
+    /// The views given by each --view option, from its values.
+    fn all(matches: &ArgMatches) -> Vec<Self> {
+        matches
+            .get_occurrences::<String>("view")
+            .into_iter()
+            .flatten()
+            .map(|mut values| Self {
+                spec: values.next().map(PathBuf::from).unwrap_or_default(),
+                data: values.next().filter(|p| !p.is_empty()).map(PathBuf::from),
+                title: values.next().filter(|t| !t.is_empty()).cloned(),
+            })
+            .collect()
+    }
 }
 
-/// All the bytes in a file.
-fn file_contents(path: &Path) -> Vec<u8> {
-    let mut handle = File::open(path).expect("file not found");
-    let mut buf = Vec::<u8>::new();
-    handle.read_to_end(&mut buf).expect("unable to read file");
-    buf
+/// Accept only paths to image files that can be exported.
+fn image_path(s: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(s);
+    match Image::from_path(&path) {
+        Some(_) => Ok(path),
+        None => Err("expected a file name ending in .svg or .png".to_string()),
+    }
 }
 
-/// A pimitive logger with millisecond timestamps.
-#[derive(Debug, Clone, Copy)]
-enum Log {
-    Enabled(Instant),
-    Disabled,
+fn main() -> wry::Result<()> {
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if args.output.is_some() {
+        // software rendering, for machines without a GPU, set before any threads start
+        std::env::set_var("WEBKIT_DISABLE_COMPOSITING_MODE", "1");
+    }
+    if args.server {
+        #[cfg(unix)]
+        let result = vega_view::server::serve(args.debug).map_err(|e| e.to_string());
+        #[cfg(not(unix))]
+        let result = Err("--server needs Unix sockets".to_string());
+        if let Err(e) = result {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if args.spec.as_deref().is_some_and(vega_view::spec::is_path) {
+        args.spec_file = args.spec.take().map(PathBuf::from);
+    }
+    let stdin_data = args.data.is_none() && args.sqlite.is_none();
+    if args.spec_file.as_deref() == Some(Path::new("-")) && stdin_data {
+        eprintln!(
+            "the spec can only be read from stdin when the data is given with --data or --sqlite"
+        );
+        std::process::exit(1);
+    }
+    if let Some(chart) = &args.chart {
+        if args.spec.is_some() || args.spec_file.is_some() || !args.view.is_empty() {
+            eprintln!("a spec can't be given with a preset chart");
+            std::process::exit(1);
+        }
+        let mut spec = chart.spec();
+        if args.flip {
+            chart::flip(&mut spec);
+        }
+        spec["data"] = serde_json::json!({ "url": "/data" });
+        args.spec = Some(spec.to_string());
+    } else if args.flip {
+        eprintln!("--flip only applies to the preset charts");
+        std::process::exit(1);
+    }
+    if !args.view.is_empty() {
+        return run_views(&args, ViewFiles::all(&matches));
+    }
+    let mut view = args.view();
+    if !view.has_spec() {
+        view = match view.infer_spec() {
+            Ok(view) => view,
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        };
+    }
+    let problems = view.check(args.check);
+    for problem in &problems {
+        eprintln!("{problem}");
+    }
+    if problems.iter().any(|p| matches!(p, Problem::Error(_))) {
+        std::process::exit(1);
+    }
+    if args.check {
+        return Ok(());
+    }
+    if args.print_spec {
+        let text = view.linked_spec().unwrap_or_default();
+        match serde_json::from_str::<serde_json::Value>(&text) {
+            Ok(spec) => println!("{spec:#}"),
+            Err(_) => println!("{text}"),
+        }
+        return Ok(());
+    }
+    if let Some(path) = &args.export_html {
+        let result = view
+            .standalone_html()
+            .map(|html| std::fs::write(path, html));
+        match result {
+            Ok(Ok(())) => return Ok(()),
+            Ok(Err(e)) => eprintln!("unable to write {}: {e}", path.display()),
+            Err(e) => eprintln!("{e}"),
+        }
+        std::process::exit(1);
+    }
+    #[cfg(unix)]
+    match view.send() {
+        Ok(true) => return Ok(()),
+        Ok(false) => {}
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+    let outcome = view.run()?;
+    if args.select_index {
+        if let Some(indices) = outcome.selected_indices {
+            println!("{}", serde_json::json!(indices));
+        }
+    } else if let Some(rows) = outcome.selected {
+        println!("{rows}");
+    }
+    std::process::exit(outcome.code);
 }
 
-impl Log {
-    fn new(enabled: bool) -> Self {
-        if enabled {
-            Self::Enabled(Instant::now())
-        } else {
-            Self::Disabled
+/// Show each of the --view options in a window of its own.
+fn run_views(args: &Args, files: Vec<ViewFiles>) -> wry::Result<()> {
+    let views = files
+        .iter()
+        .map(|files| {
+            let mut view = args.view().spec_file(&files.spec);
+            if let Some(path) = &files.data {
+                view = view.data_file(path);
+            }
+            let title = files.title.clone().or_else(|| args.title.clone());
+            view.title(title.unwrap_or_else(|| files.spec.display().to_string()))
+        })
+        .collect::<Vec<_>>();
+    let mut failed = false;
+    for (files, view) in files.iter().zip(&views) {
+        for problem in view.check(args.check) {
+            eprintln!("{}: {problem}", files.spec.display());
+            failed |= matches!(problem, Problem::Error(_));
         }
     }
-    fn print(self, item: impl Debug) {
-        match self {
-            Self::Enabled(start) => eprintln!("{} {item:?}", start.elapsed().as_millis()),
-            Self::Disabled => {}
+    if failed {
+        std::process::exit(1);
+    }
+    if args.check {
+        return Ok(());
+    }
+    #[cfg(unix)]
+    match vega_view::server::send_all(&views) {
+        Ok(true) => return Ok(()),
+        Ok(false) => {}
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
         }
     }
+    let outcome = vega_view::run_all(views)?;
+    std::process::exit(outcome.code);
 }
@@ -0,0 +1,230 @@
+use crate::{
+    ipc,
+    view::{Log, ViewBuilder},
+    window::{run_script, Outcome, RENDER_ERROR, SCHEME},
+};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io,
+    sync::{Arc, Mutex},
+};
+use tao::{
+    dpi::PhysicalSize,
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoopBuilder, EventLoopWindowTarget},
+    platform::run_return::EventLoopExtRunReturn,
+    window::{Window, WindowBuilder},
+};
+use wry::{
+    http::{Request, Response, StatusCode},
+    WebContext, WebView, WebViewBuilder,
+};
+
+/// Whether custom protocols belong to the web context, which then takes one
+/// handler for each, rather than to each webview.
+const CONTEXT_PROTOCOLS: bool = cfg!(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+));
+
+/// Windows whose pages share a web context and one protocol handler,
+/// which serves the view of each page at `/view/<id>/`.
+pub(crate) struct Pages {
+    context: WebContext,
+    views: Arc<Mutex<HashMap<usize, Arc<ViewBuilder>>>>,
+    next: usize,
+    log: Log,
+}
+
+/// A window showing a view.
+pub(crate) struct Page {
+    pub(crate) id: usize,
+    pub(crate) window: Window,
+    pub(crate) webview: WebView,
+}
+
+impl Pages {
+    pub(crate) fn new(log: Log) -> Self {
+        Self {
+            context: WebContext::default(),
+            views: Arc::default(),
+            next: 0,
+            log,
+        }
+    }
+
+    /// Open a window showing the view.  Messages from its page are passed
+    /// to `ipc` with the id of the page.
+    pub(crate) fn open<T>(
+        &mut self,
+        target: &EventLoopWindowTarget<T>,
+        view: ViewBuilder,
+        ipc: impl Fn(usize, ipc::Message) + 'static,
+    ) -> wry::Result<Page> {
+        let id = self.next;
+        self.next += 1;
+        let window = WindowBuilder::new()
+            .with_title(view.title.as_deref().unwrap_or("Vega View"))
+            .with_inner_size(PhysicalSize::new(view.width, view.height))
+            .with_theme(
+                view.theme
+                    .filter(|t| t.is_dark())
+                    .map(|_| tao::window::Theme::Dark),
+            )
+            .build(target)
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        self.insert(id, &window, view);
+        let mut builder = WebViewBuilder::new(&window).with_web_context(&mut self.context);
+        if id == 0 || !CONTEXT_PROTOCOLS {
+            let views = self.views.clone();
+            let log = self.log;
+            builder =
+                builder.with_custom_protocol(SCHEME.to_string(), move |r| route(&views, log, r));
+        }
+        let webview = builder
+            .with_ipc_handler(move |r| match ipc::Message::parse(r.body()) {
+                Ok(message) => ipc(id, message),
+                Err(e) => eprintln!("invalid message from page: {e}"),
+            })
+            .with_url(format!("{SCHEME}://local/view/{id}/page"))
+            .with_devtools(true)
+            .build()?;
+        Ok(Page {
+            id,
+            window,
+            webview,
+        })
+    }
+
+    /// Show a view in a page in place of its view, without reloading the page.
+    pub(crate) fn replace(&self, page: &Page, view: ViewBuilder) {
+        page.window
+            .set_title(view.title.as_deref().unwrap_or("Vega View"));
+        self.insert(page.id, &page.window, view);
+        run_script(&page.webview, "show()");
+    }
+
+    /// Stop serving the view of a page that has been closed.
+    pub(crate) fn close(&self, page: Page) {
+        self.views.lock().unwrap().remove(&page.id);
+    }
+
+    /// Serve a view for the page with the id, in the window.
+    fn insert(&self, id: usize, window: &Window, view: ViewBuilder) {
+        let mut view = view;
+        view.theme = view.theme.map(|theme| theme.resolve(window.theme()));
+        view.base = Some(format!("/view/{id}"));
+        self.views.lock().unwrap().insert(id, Arc::new(view));
+    }
+}
+
+/// Respond to a request for `/view/<id>/...` with the view of that page.
+fn route(
+    views: &Mutex<HashMap<usize, Arc<ViewBuilder>>>,
+    log: Log,
+    mut request: Request<Vec<u8>>,
+) -> Response<Cow<'static, [u8]>> {
+    let routed = request
+        .uri()
+        .path()
+        .strip_prefix("/view/")
+        .and_then(|path| path.split_once('/'))
+        .and_then(|(id, path)| {
+            let view = views.lock().unwrap().get(&id.parse().ok()?).cloned()?;
+            Some((view, format!("/{path}").parse().ok()?))
+        });
+    match routed {
+        Some((view, uri)) => {
+            *request.uri_mut() = uri;
+            view.handler(log, request)
+        }
+        None => {
+            log.print(&request);
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Cow::from("Not found".as_bytes()))
+                .unwrap()
+        }
+    }
+}
+
+/// Events sent to the event loop of [`run_all`].
+#[derive(Debug)]
+enum PageEvent {
+    /// A message from the page with the id.
+    Message(usize, ipc::Message),
+}
+
+/// Show several views at once, each in a window of its own, returning when they
+/// have all been closed.  The windows share one event loop and one web context.
+/// This must be called on the main thread.
+///
+/// Errors in the views are reported with the window title.  When any of the views
+/// exits on an error, the first error closes all the windows.  Streaming, watching,
+/// selecting and exporting need a view to be run by itself.
+pub fn run_all(views: impl IntoIterator<Item = ViewBuilder>) -> wry::Result<Outcome> {
+    let views = views.into_iter().collect::<Vec<_>>();
+    let log = Log::new(views.iter().any(|view| view.debug));
+    let exit_on_error = views.iter().any(|view| view.exit_on_error);
+    let mut event_loop = EventLoopBuilder::<PageEvent>::with_user_event().build();
+    let mut pages = Pages::new(log);
+    let mut windows = HashMap::new();
+    for view in views {
+        let proxy = event_loop.create_proxy();
+        let page = pages.open(&event_loop, view, move |id, message| {
+            let _ = proxy.send_event(PageEvent::Message(id, message));
+        })?;
+        windows.insert(page.window.id(), page);
+    }
+    if windows.is_empty() {
+        return Ok(Outcome::default());
+    }
+
+    let code = event_loop.run_return(|event, _, control_flow| {
+        *control_flow = ControlFlow::Wait;
+
+        match event {
+            Event::UserEvent(PageEvent::Message(id, ipc::Message::Error { message })) => {
+                eprintln!("error: {}: {message}", title(&windows, id));
+                if exit_on_error {
+                    *control_flow = ControlFlow::ExitWithCode(RENDER_ERROR);
+                }
+            }
+            Event::UserEvent(PageEvent::Message(id, ipc::Message::Warning { message })) => {
+                eprintln!("warning: {}: {message}", title(&windows, id));
+            }
+            Event::WindowEvent {
+                window_id,
+                event: WindowEvent::CloseRequested,
+                ..
+            } => {
+                log.print(&event);
+                if let Some(page) = windows.remove(&window_id) {
+                    pages.close(page);
+                }
+                if windows.is_empty() {
+                    *control_flow = ControlFlow::Exit;
+                }
+            }
+            Event::UserEvent(PageEvent::Message(..)) => log.print(&event),
+            _ => {}
+        }
+    });
+    Ok(Outcome {
+        code,
+        ..Outcome::default()
+    })
+}
+
+/// The title of the window of a page, to tell which view a message is about.
+pub(crate) fn title<K>(windows: &HashMap<K, Page>, id: usize) -> String {
+    windows
+        .values()
+        .find(|page| page.id == id)
+        .map(|page| page.window.title())
+        .unwrap_or_default()
+}
@@ -0,0 +1,175 @@
+use jsonschema::{error::ValidationErrorKind, ValidationError, Validator};
+use serde_json::Value;
+use std::{fmt, sync::OnceLock};
+
+const SCHEMA: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/vega-lite-schema.json"));
+
+/// A way in which a specification does not conform to the schema.
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Check a vega-lite specification against the bundled schema.
+/// Returns `None` if vega-view was built without the schema.
+pub fn validate(spec: &Value) -> Option<Vec<Problem>> {
+    let problems = validator()?
+        .iter_errors(spec)
+        .flat_map(|e| problems(&e))
+        .collect();
+    Some(problems)
+}
+
+/// The validator for the bundled schema, compiled once.
+fn validator() -> Option<&'static Validator> {
+    static VALIDATOR: OnceLock<Option<Validator>> = OnceLock::new();
+    VALIDATOR
+        .get_or_init(|| {
+            let schema = serde_json::from_slice(SCHEMA).ok()?;
+            Some(Validator::new(&schema).expect("the bundled schema is invalid"))
+        })
+        .as_ref()
+}
+
+/// The most specific problems behind a validation error.
+///
+/// When no alternative of an `anyOf` or `oneOf` matches, the one that got
+/// furthest into the specification, with the fewest problems, is assumed
+/// to be the one intended.
+fn problems(error: &ValidationError) -> Vec<Problem> {
+    match error.kind() {
+        ValidationErrorKind::AnyOf { context } | ValidationErrorKind::OneOfNotValid { context } => {
+            // the first of equally good alternatives, which is usually the simplest
+            let intended = context
+                .iter()
+                .rev()
+                .filter(|errors| !errors.is_empty())
+                .max_by_key(|errors| {
+                    let depth = errors.iter().map(depth).max().unwrap_or(0);
+                    (depth, usize::MAX - errors.len())
+                });
+            match intended {
+                Some(errors) => errors.iter().flat_map(problems).collect(),
+                None => vec![problem(error)],
+            }
+        }
+        _ => vec![problem(error)],
+    }
+}
+
+fn problem(error: &ValidationError) -> Problem {
+    let message = match error.kind() {
+        ValidationErrorKind::Enum { options } => match options {
+            Value::Array(options) => {
+                let options = options.iter().map(Value::to_string).collect::<Vec<_>>();
+                format!("expected one of {}", options.join(", "))
+            }
+            options => format!("expected {options}"),
+        },
+        ValidationErrorKind::Constant { expected_value } => format!("expected {expected_value}"),
+        _ => error.to_string(),
+    };
+    Problem {
+        path: dotted(error.instance_path().as_str()),
+        message,
+    }
+}
+
+/// The number of steps into the specification where the error was found.
+fn depth(error: &ValidationError) -> usize {
+    error.instance_path().as_str().matches('/').count()
+}
+
+/// A JSON pointer written as a path such as `layer[0].encoding.x`.
+fn dotted(pointer: &str) -> String {
+    let mut path = String::new();
+    for segment in pointer.split('/').skip(1) {
+        let segment = segment.replace("~1", "/").replace("~0", "~");
+        if segment.parse::<usize>().is_ok() {
+            path.push_str(&format!("[{segment}]"));
+        } else {
+            if !path.is_empty() {
+                path.push('.');
+            }
+            path.push_str(&segment);
+        }
+    }
+    if path.is_empty() {
+        "spec".to_string()
+    } else {
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn check(schema: &Value, spec: &Value) -> Vec<String> {
+        Validator::new(schema)
+            .unwrap()
+            .iter_errors(spec)
+            .flat_map(|e| problems(&e))
+            .map(|p| p.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(dotted(""), "spec");
+        assert_eq!(dotted("/mark"), "mark");
+        assert_eq!(dotted("/layer/0/encoding/x"), "layer[0].encoding.x");
+        assert_eq!(dotted("/0/a~1b/c~0d"), "[0].a/b.c~d");
+    }
+
+    #[test]
+    fn enums() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "type": { "enum": ["quantitative", "nominal"] } },
+        });
+        assert_eq!(
+            check(&schema, &json!({ "type": "quant" })),
+            [r#"type: expected one of "quantitative", "nominal""#]
+        );
+    }
+
+    #[test]
+    fn narrowed() {
+        // a mark is either a name or an object, and the object was intended
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "mark": {
+                    "anyOf": [
+                        { "enum": ["bar", "line"] },
+                        {
+                            "type": "object",
+                            "properties": {
+                                "type": { "enum": ["bar", "line"] },
+                                "tooltip": { "type": "boolean" },
+                            },
+                        },
+                    ],
+                },
+            },
+        });
+        assert_eq!(
+            check(&schema, &json!({ "mark": { "type": "bar", "tooltip": 1 } })),
+            ["mark.tooltip: 1 is not of type \"boolean\""]
+        );
+        assert_eq!(
+            check(&schema, &json!({ "mark": "pie" })),
+            [r#"mark: expected one of "bar", "line""#]
+        );
+        assert!(check(&schema, &json!({ "mark": "bar" })).is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// An entry in the store of a vega-lite selection parameter.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Tuple {
+    fields: Vec<Field>,
+    values: Vec<Value>,
+}
+
+/// A field tested by a selection and how it is tested.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Field {
+    field: String,
+    #[serde(rename = "type")]
+    test: String,
+}
+
+/// The current contents of each selection parameter.
+#[derive(Debug, Default)]
+pub struct Selections(HashMap<String, Vec<Tuple>>);
+
+impl Selections {
+    pub fn update(&mut self, name: String, store: Vec<Tuple>) {
+        self.0.insert(name, store);
+    }
+
+    /// The records in any of the selections.
+    pub fn select(&self, records: Vec<Value>) -> Vec<Value> {
+        let indices = self.indices(&records);
+        records
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| indices.binary_search(i).is_ok())
+            .map(|(_, record)| record)
+            .collect()
+    }
+
+    /// The positions of the records in any of the selections, in order.
+    pub fn indices(&self, records: &[Value]) -> Vec<usize> {
+        records
+            .iter()
+            .enumerate()
+            .filter(|(i, record)| {
+                self.0
+                    .values()
+                    .flatten()
+                    .any(|tuple| tuple.contains(*i, record))
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+impl Tuple {
+    /// Whether the record at the given position in the data passes every test,
+    /// following vega-lite's `vlSelectionTest`.
+    fn contains(&self, index: usize, record: &Value) -> bool {
+        self.fields.iter().zip(&self.values).all(|(field, value)| {
+            // point selections without fields identify records by position
+            if field.field == "_vgsid_" {
+                return value.as_u64() == Some(index as u64 + 1);
+            }
+            let Some(datum) = lookup(record, &field.field) else {
+                return false;
+            };
+            match (field.test.as_str(), value.as_array()) {
+                ("E", _) => equal(datum, value),
+                (test, Some(range)) if range.len() == 2 => {
+                    let (Some(x), Some(lo), Some(hi)) =
+                        (number(datum), number(&range[0]), number(&range[1]))
+                    else {
+                        return false;
+                    };
+                    let (lo, hi) = (lo.min(hi), lo.max(hi));
+                    match test {
+                        "R-E" => lo < x && x < hi,
+                        "R-LE" => lo < x && x <= hi,
+                        "R-RE" => lo <= x && x < hi,
+                        _ => lo <= x && x <= hi,
+                    }
+                }
+                _ => false,
+            }
+        })
+    }
+}
+
+/// The value of a field, which may be a path to a nested value.
+fn lookup<'a>(record: &'a Value, field: &str) -> Option<&'a Value> {
+    record
+        .get(field)
+        .or_else(|| field.split('.').try_fold(record, |v, key| v.get(key)))
+}
+
+fn equal(datum: &Value, value: &Value) -> bool {
+    match (number(datum), value.as_f64()) {
+        (Some(x), Some(y)) => x == y,
+        _ => datum == value,
+    }
+}
+
+/// The numeric value of a datum as vega would see it, with dates as timestamps.
+fn number(datum: &Value) -> Option<f64> {
+    match datum {
+        Value::Number(n) => n.as_f64(),
+        Value::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+        Value::String(s) => timestamp(s).or_else(|| s.parse().ok()),
+        _ => None,
+    }
+}
+
+/// Milliseconds since the epoch for a date string, which is UTC for a date
+/// alone and local time for a date and time without an offset, as in javascript.
+fn timestamp(s: &str) -> Option<f64> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Some(t.timestamp_millis() as f64);
+    }
+    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Some(d.and_hms_opt(0, 0, 0)?.and_utc().timestamp_millis() as f64);
+    }
+    let t = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f").ok()?;
+    Some(Local.from_local_datetime(&t).earliest()?.timestamp_millis() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tuple(fields: &[(&str, &str)], values: Value) -> Tuple {
+        let fields = fields
+            .iter()
+            .map(|(field, test)| json!({ "field": field, "type": test }))
+            .collect::<Vec<_>>();
+        serde_json::from_value(json!({ "fields": fields, "values": values })).unwrap()
+    }
+
+    #[test]
+    fn point() {
+        let record = json!({ "make": "ford", "price": 10, "used": true, "site": { "id": 3 } });
+        let equal = tuple(&[("make", "E"), ("price", "E")], json!(["ford", 10.0]));
+        assert!(equal.contains(0, &record));
+        assert!(!tuple(&[("make", "E")], json!(["fiat"])).contains(0, &record));
+        assert!(tuple(&[("used", "E")], json!([1])).contains(0, &record));
+        assert!(tuple(&[("site.id", "E")], json!([3])).contains(0, &record));
+        assert!(!tuple(&[("missing", "E")], json!([null])).contains(0, &record));
+        // records picked by position are counted from one
+        let position = tuple(&[("_vgsid_", "E")], json!([2]));
+        assert!(position.contains(1, &record));
+        assert!(!position.contains(0, &record));
+    }
+
+    #[test]
+    fn interval() {
+        let record = json!({ "price": 10, "date": "2024-06-01" });
+        let range = |test, lo, hi| tuple(&[("price", test)], json!([[lo, hi]]));
+        assert!(range("R", 10, 20).contains(0, &record));
+        assert!(range("R", 20, 5).contains(0, &record));
+        assert!(!range("R-E", 10, 20).contains(0, &record));
+        assert!(!range("R-LE", 10, 20).contains(0, &record));
+        assert!(range("R-RE", 10, 20).contains(0, &record));
+        assert!(range("R-LE", 5, 10).contains(0, &record));
+        assert!(!range("R", 11, 20).contains(0, &record));
+
+        // 2024-06-01T00:00:00Z and the following day
+        let dates = tuple(
+            &[("date", "R")],
+            json!([[1717200000000_i64, 1717286400000_i64]]),
+        );
+        assert!(dates.contains(0, &record));
+        assert!(!tuple(&[("date", "R")], json!([[0, 1]])).contains(0, &record));
+    }
+
+    #[test]
+    fn select() {
+        let mut selections = Selections::default();
+        selections.update("pick".into(), vec![tuple(&[("a", "E")], json!([1]))]);
+        selections.update("brush".into(), vec![tuple(&[("a", "R")], json!([[3, 4]]))]);
+        let records = (0..5).map(|a| json!({ "a": a })).collect::<Vec<_>>();
+        assert_eq!(selections.indices(&records), [1, 3, 4]);
+        assert_eq!(
+            selections.select(records),
+            [json!({ "a": 1 }), json!({ "a": 3 }), json!({ "a": 4 })]
+        );
+    }
+}
@@ -0,0 +1,309 @@
+//! A long-lived vega-view process showing the views sent to it over a Unix socket,
+//! in new windows or in place of the view in a named window.
+
+use crate::{
+    ipc,
+    pages::{title, Page, Pages},
+    spec::Mode,
+    theme::Theme,
+    view::{Log, ViewBuilder},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::{
+        fs::{DirBuilderExt, FileTypeExt, MetadataExt, PermissionsExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    thread,
+};
+use tao::{
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoopBuilder, EventLoopProxy},
+    platform::run_return::EventLoopExtRunReturn,
+    window::WindowId,
+};
+
+/// A view sent to the server, with its data inlined in the spec.
+#[derive(Serialize, Deserialize, Debug)]
+struct Show {
+    spec: Value,
+    mode: Mode,
+    theme: Option<Theme>,
+    title: Option<String>,
+    target: Option<String>,
+    width: u32,
+    height: u32,
+}
+
+impl Show {
+    /// The view to serve to the page.
+    fn view(&self) -> ViewBuilder {
+        let mut view = ViewBuilder::new()
+            .spec_value(&self.spec)
+            .mode(self.mode)
+            .size(self.width, self.height);
+        if let Some(theme) = self.theme {
+            view = view.theme(theme);
+        }
+        if let Some(title) = &self.title {
+            view = view.title(title);
+        }
+        view
+    }
+}
+
+/// Events sent to the event loop from other threads.
+#[derive(Debug)]
+enum ServerEvent {
+    /// A view has been sent to the server.
+    Show(Show),
+    /// A message from the page with the id.
+    Message(usize, ipc::Message),
+}
+
+/// The socket a vega-view server listens on: `vega-view.sock` in a `vega-view`
+/// directory of `$XDG_RUNTIME_DIR`, or else in a `vega-view-<uid>` directory of the
+/// temporary directory.  The directory is created, readable only by the user, if it
+/// does not exist, and it is an error if it exists but belongs to anyone else.
+pub fn socket_path() -> io::Result<PathBuf> {
+    let uid = uid();
+    let dir = match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir).join("vega-view"),
+        _ => env::temp_dir().join(format!("vega-view-{uid}")),
+    };
+    match fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => {
+            let message = format!("unable to create {}: {e}", dir.display());
+            return Err(io::Error::new(e.kind(), message));
+        }
+        _ => {}
+    }
+    let metadata = fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        let message = format!(
+            "{} is not a directory readable only by this user",
+            dir.display()
+        );
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, message));
+    }
+    Ok(dir.join("vega-view.sock"))
+}
+
+/// Whether the file is a socket made by this user, which only a server
+/// of theirs could have bound.
+fn own_socket(path: &Path) -> bool {
+    fs::symlink_metadata(path)
+        .is_ok_and(|metadata| metadata.file_type().is_socket() && metadata.uid() == uid())
+}
+
+/// The effective user id of this process.
+fn uid() -> u32 {
+    // SAFETY: geteuid has no preconditions and cannot fail
+    unsafe { libc::geteuid() }
+}
+
+impl ViewBuilder {
+    /// Show the view in a window of a running vega-view server, returning once
+    /// the server has it.  Gives `false`, leaving the view to be run, when no
+    /// server is running or the view needs a process of its own: to stream, watch,
+    /// select, export, exit on an error or use a custom page or script.
+    /// With debug logging the reason a view was not sent is logged.
+    pub fn send(&self) -> Result<bool, String> {
+        let log = Log::new(self.debug);
+        if let Some(reason) = self.own_process() {
+            log.print(format!("not sent to a vega-view server: {reason}"));
+            return Ok(false);
+        }
+        let path = match socket_path() {
+            Ok(path) => path,
+            Err(e) => {
+                log.print(format!("not sent to a vega-view server: {e}"));
+                return Ok(false);
+            }
+        };
+        if !own_socket(&path) {
+            log.print(format!(
+                "not sent to a vega-view server: no socket of this user at {}",
+                path.display()
+            ));
+            return Ok(false);
+        }
+        let mut stream = match UnixStream::connect(&path) {
+            Ok(stream) => stream,
+            Err(e) => {
+                log.print(format!(
+                    "not sent to a vega-view server: none at {}: {e}",
+                    path.display()
+                ));
+                return Ok(false);
+            }
+        };
+        let spec = self.inlined_spec().map_err(|e| e.to_string())?;
+        let show = Show {
+            mode: self.mode.unwrap_or_else(|| Mode::detect(&spec)),
+            spec,
+            theme: self.theme,
+            title: self.title.clone(),
+            target: self.target.clone(),
+            width: self.width,
+            height: self.height,
+        };
+        let mut reply = String::new();
+        serde_json::to_writer(&mut stream, &show)
+            .map_err(io::Error::from)
+            .and_then(|()| stream.write_all(b"\n"))
+            .and_then(|()| BufReader::new(stream).read_line(&mut reply))
+            .map_err(|e| format!("unable to send the view to the vega-view server: {e}"))?;
+        match reply.trim_end() {
+            "ok" => Ok(true),
+            error => Err(format!("the vega-view server refused the view: {error}")),
+        }
+    }
+
+    /// Why the view can't be shown by a server, if it can't.
+    fn own_process(&self) -> Option<&'static str> {
+        [
+            (self.stream, "it streams from stdin"),
+            (self.watch, "it watches its files"),
+            (self.select, "it gives the selected rows"),
+            (self.exit_on_error, "it exits on an error"),
+            (self.output.is_some(), "it is exported"),
+            (self.page.is_some(), "it has a custom page"),
+            (self.script.is_some(), "it has a custom script"),
+        ]
+        .into_iter()
+        .find_map(|(needed, reason)| needed.then_some(reason))
+    }
+}
+
+/// Show all the views in windows of a running vega-view server, as [`ViewBuilder::send`]
+/// does, or none of them.  Gives `false`, leaving the views to be run, when no server
+/// is running or any of the views needs a process of its own.
+pub fn send_all(views: &[ViewBuilder]) -> Result<bool, String> {
+    let log = Log::new(views.iter().any(|view| view.debug));
+    if let Some(reason) = views.iter().find_map(ViewBuilder::own_process) {
+        log.print(format!(
+            "not sent to a vega-view server: a view needs its own process, {reason}"
+        ));
+        return Ok(false);
+    }
+    for (i, view) in views.iter().enumerate() {
+        match view.send()? {
+            true => {}
+            // no server is running
+            false if i == 0 => return Ok(false),
+            false => {
+                return Err(
+                    "the vega-view server stopped before all the views were sent".to_string(),
+                )
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// Listen for views on the socket and show them, until the process is stopped.
+pub fn serve(debug: bool) -> wry::Result<()> {
+    let log = Log::new(debug);
+    let path = socket_path()?;
+    if UnixStream::connect(&path).is_ok() {
+        let message = format!(
+            "a vega-view server is already running at {}",
+            path.display()
+        );
+        return Err(io::Error::new(io::ErrorKind::AddrInUse, message).into());
+    }
+    // left behind by a server that was stopped
+    let _ = fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    eprintln!("listening on {}", path.display());
+
+    let mut event_loop = EventLoopBuilder::<ServerEvent>::with_user_event().build();
+    let proxy = event_loop.create_proxy();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let proxy = proxy.clone();
+            thread::spawn(move || receive(stream, &proxy));
+        }
+    });
+
+    let mut pages = Pages::new(log);
+    let mut windows = HashMap::<WindowId, Page>::new();
+    let mut targets = HashMap::<String, WindowId>::new();
+    let proxy = event_loop.create_proxy();
+    event_loop.run_return(|event, target, control_flow| {
+        *control_flow = ControlFlow::Wait;
+
+        match event {
+            Event::UserEvent(ServerEvent::Show(show)) => {
+                log.print(format!("Show {:?}", show.title));
+                let shown = show
+                    .target
+                    .as_ref()
+                    .and_then(|name| windows.get(targets.get(name)?));
+                if let Some(page) = shown {
+                    pages.replace(page, show.view());
+                    return;
+                }
+                let proxy = proxy.clone();
+                let opened = pages.open(target, show.view(), move |id, message| {
+                    let _ = proxy.send_event(ServerEvent::Message(id, message));
+                });
+                match opened {
+                    Ok(page) => {
+                        if let Some(name) = show.target {
+                            targets.insert(name, page.window.id());
+                        }
+                        windows.insert(page.window.id(), page);
+                    }
+                    Err(e) => eprintln!("unable to open a window: {e}"),
+                }
+            }
+            Event::UserEvent(ServerEvent::Message(id, ipc::Message::Error { message })) => {
+                eprintln!("error: {}: {message}", title(&windows, id));
+            }
+            Event::UserEvent(ServerEvent::Message(id, ipc::Message::Warning { message })) => {
+                eprintln!("warning: {}: {message}", title(&windows, id));
+            }
+            Event::WindowEvent {
+                window_id,
+                event: WindowEvent::CloseRequested,
+                ..
+            } => {
+                log.print(&event);
+                if let Some(page) = windows.remove(&window_id) {
+                    pages.close(page);
+                }
+                targets.retain(|_, id| *id != window_id);
+            }
+            Event::UserEvent(ServerEvent::Message(..)) => log.print(&event),
+            _ => {}
+        }
+    });
+    Ok(())
+}
+
+/// Read a view from a client and pass it to the event loop, replying `ok`
+/// or with the reason it was not accepted.
+fn receive(stream: UnixStream, proxy: &EventLoopProxy<ServerEvent>) {
+    let mut line = String::new();
+    let mut reader = BufReader::new(&stream);
+    if let Err(e) = reader.read_line(&mut line) {
+        eprintln!("unable to read from a client: {e}");
+        return;
+    }
+    let reply = match serde_json::from_str::<Show>(&line) {
+        Ok(show) => match proxy.send_event(ServerEvent::Show(show)) {
+            Ok(()) => "ok".to_string(),
+            Err(_) => "the server is stopping".to_string(),
+        },
+        Err(e) => format!("invalid view: {e}"),
+    };
+    let _ = (&stream).write_all(format!("{reply}\n").as_bytes());
+}
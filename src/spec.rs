@@ -0,0 +1,676 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{path::Path, str::FromStr};
+
+/// The language of a specification.
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    /// A Vega specification, with a `data` array.
+    Vega,
+    /// A Vega-Lite specification.
+    VegaLite,
+}
+
+impl Mode {
+    /// The language named by the `$schema` of a specification, Vega-Lite by default.
+    pub fn detect(spec: &Value) -> Self {
+        match spec.get("$schema").and_then(Value::as_str) {
+            Some(schema) if schema.contains("/schema/vega/") => Self::Vega,
+            _ => Self::VegaLite,
+        }
+    }
+
+    /// The name of the mode, as vega-embed knows it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Vega => "vega",
+            Self::VegaLite => "vega-lite",
+        }
+    }
+}
+
+/// Parse a specification, or `Null` if it is not valid JSON.
+pub fn parse(spec: &str) -> Value {
+    serde_json::from_str(spec).unwrap_or(Value::Null)
+}
+
+/// Whether a spec argument names a file rather than giving the JSON.
+pub fn is_path(spec: &str) -> bool {
+    !spec.trim_start().starts_with('{') && Path::new(spec).is_file()
+}
+
+/// Whether a specification file is YAML, by its extension.
+pub fn is_yaml(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("yaml") || e.eq_ignore_ascii_case("yml"))
+}
+
+/// Convert the text of a YAML (`.yaml`, `.yml`) or JSON5 (`.json5`) specification file
+/// to JSON.  The text of other files is assumed to be JSON already.  A specification
+/// read from stdin, `-`, is taken as JSON, or else JSON5, or else YAML.
+pub fn to_json(path: &Path, text: String) -> Result<String, String> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    match extension.to_ascii_lowercase().as_str() {
+        "yaml" | "yml" => from_yaml(&text),
+        "json5" => from_json5(&text),
+        _ if path == Path::new("-") => {
+            if serde_json::from_str::<Value>(&text).is_ok() {
+                Ok(text)
+            } else {
+                from_json5(&text).or_else(|_| from_yaml(&text))
+            }
+        }
+        _ => Ok(text),
+    }
+}
+
+fn from_yaml(text: &str) -> Result<String, String> {
+    serde_yaml_ng::from_str::<Value>(text)
+        .map(|spec| spec.to_string())
+        .map_err(|e| format!("the spec is not valid YAML: {e}"))
+}
+
+fn from_json5(text: &str) -> Result<String, String> {
+    json5::from_str::<Value>(text)
+        .map(|spec| spec.to_string())
+        .map_err(|e| format!("the spec is not valid JSON5: {e}"))
+}
+
+/// A change to one property of a specification, given on the command line as
+/// `path=value`.  The path is dotted, `encoding.y.scale.type`, or a JSON pointer,
+/// `/encoding/y/scale/type`.  The value is JSON, or else taken as a string.
+#[derive(Clone, Debug)]
+pub struct Patch {
+    pub path: Vec<String>,
+    pub value: Value,
+}
+
+impl FromStr for Patch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((path, value)) = s.split_once('=').filter(|(path, _)| !path.is_empty()) else {
+            return Err(format!("expected path=value, found '{s}'"));
+        };
+        let path = match path.strip_prefix('/') {
+            Some(pointer) => pointer
+                .split('/')
+                .map(|key| key.replace("~1", "/").replace("~0", "~"))
+                .collect(),
+            None => path.split('.').map(String::from).collect::<Vec<_>>(),
+        };
+        if path.iter().any(String::is_empty) {
+            return Err(format!("expected a path with no empty keys, found '{s}'"));
+        }
+        let value =
+            serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+        Ok(Self { path, value })
+    }
+}
+
+impl Patch {
+    /// Set the property, adding any objects missing on the way to it, or arrays
+    /// where the next key is an index or `-`.
+    /// A mark given by its type alone, `mark: 'bar'`, becomes `mark: {type: 'bar'}`.
+    pub fn apply(&self, spec: &mut Value) -> Result<(), String> {
+        let mut target = spec;
+        let mut parent = "spec";
+        for key in &self.path {
+            match &*target {
+                Value::Null if key == "-" || key.parse::<usize>().is_ok() => {
+                    *target = Value::Array(Vec::new())
+                }
+                Value::Null => *target = Value::Object(Default::default()),
+                Value::String(mark) if parent == "mark" => {
+                    *target = serde_json::json!({ "type": mark })
+                }
+                _ => {}
+            }
+            target = match target {
+                Value::Object(map) => map.entry(key.as_str()).or_insert(Value::Null),
+                Value::Array(items) => {
+                    // - appends to the array, as in a JSON patch
+                    if key == "-" {
+                        items.push(Value::Null);
+                    }
+                    let index = match key.as_str() {
+                        "-" => Some(items.len() - 1),
+                        _ => key.parse::<usize>().ok(),
+                    };
+                    index
+                        .and_then(|i| items.get_mut(i))
+                        .ok_or_else(|| self.error(&format!("{key} is not an index of {parent}")))?
+                }
+                _ => return Err(self.error(&format!("{parent} is not an object"))),
+            };
+            parent = key;
+        }
+        *target = self.value.clone();
+        Ok(())
+    }
+
+    fn error(&self, problem: &str) -> String {
+        format!("unable to set {}: {problem}", self.path.join("."))
+    }
+}
+
+/// Merge a vega config into the `config` of a specification, overriding its settings.
+pub fn merge_config(spec: &mut Value, config: &Value) {
+    if let Value::Object(map) = spec {
+        let own = map
+            .entry("config")
+            .or_insert_with(|| Value::Object(Default::default()));
+        merge(own, config);
+    }
+}
+
+/// Deep merge one JSON value over another.
+fn merge(base: &mut Value, over: &Value) {
+    match (base, over) {
+        (Value::Object(base), Value::Object(over)) => {
+            for (key, value) in over {
+                merge(base.entry(key.as_str()).or_insert(Value::Null), value);
+            }
+        }
+        (base, over) => *base = over.clone(),
+    }
+}
+
+/// Replace each `data: {name: ...}` entry referring to one of the named datasets
+/// with a url for the dataset, `/data/<name>`.
+pub fn link_datasets(spec: &mut Value, names: &[&str]) {
+    match spec {
+        Value::Object(map) => {
+            if let Some(Value::Object(data)) = map.get_mut("data") {
+                let linked = data
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .filter(|name| names.contains(name))
+                    .map(|name| format!("/data/{name}"));
+                if let Some(url) = linked {
+                    data.remove("name");
+                    data.insert("url".to_string(), Value::String(url));
+                }
+            }
+            map.values_mut().for_each(|v| link_datasets(v, names));
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| link_datasets(v, names)),
+        _ => {}
+    }
+}
+
+/// Link the entries of a Vega `data` array that have no source of their own:
+/// those named for one of the datasets to the dataset url, `/data/<name>`,
+/// and the first of the rest to `/data`, unless another entry loads it already.
+pub fn link_vega_data(spec: &mut Value, names: &[&str]) {
+    let Some(Value::Array(entries)) = spec.get_mut("data") else {
+        return;
+    };
+    let mut main = !entries
+        .iter()
+        .any(|e| e.get("url").and_then(Value::as_str) == Some("/data"));
+    for entry in entries.iter_mut().filter_map(Value::as_object_mut) {
+        if ["url", "values", "source"]
+            .iter()
+            .any(|k| entry.contains_key(*k))
+        {
+            continue;
+        }
+        let name = entry
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let url = if names.contains(&name) {
+            format!("/data/{name}")
+        } else if main {
+            main = false;
+            "/data".to_string()
+        } else {
+            continue;
+        };
+        entry.insert("url".to_string(), Value::String(url));
+    }
+}
+
+/// Replace the top level data of a specification with a named data source,
+/// whose contents are supplied through the view API.
+pub fn name_data(spec: &mut Value, name: &str) {
+    if let Value::Object(map) = spec {
+        map.insert("data".to_string(), serde_json::json!({ "name": name }));
+    }
+}
+
+/// Replace each data url in a specification with the values found for it, if any.
+pub fn inline_data<E>(
+    spec: &mut Value,
+    values: &mut impl FnMut(&str) -> Result<Option<Value>, E>,
+) -> Result<(), E> {
+    match spec {
+        Value::Object(map) => {
+            let found = match map.get("url").and_then(Value::as_str) {
+                Some(url) => values(url)?,
+                None => None,
+            };
+            if let Some(found) = found {
+                map.remove("url");
+                map.insert("values".to_string(), found);
+            }
+            for v in map.values_mut() {
+                inline_data(v, values)?;
+            }
+        }
+        Value::Array(items) => {
+            for v in items {
+                inline_data(v, values)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Prefix the root-relative urls in a specification, such as `/data`, with a base path.
+pub fn base_urls(spec: &mut Value, base: &str) {
+    match spec {
+        Value::Object(map) => {
+            if let Some(Value::String(url)) = map.get_mut("url") {
+                if url.starts_with('/') && !url.starts_with("//") {
+                    url.insert_str(0, base);
+                }
+            }
+            map.values_mut().for_each(|v| base_urls(v, base));
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| base_urls(v, base)),
+        _ => {}
+    }
+}
+
+/// Whether a specification loads the data served at `/data`.
+pub fn uses_data(spec: &Value) -> bool {
+    match spec {
+        Value::Object(map) => {
+            map.get("url").and_then(Value::as_str) == Some("/data") || map.values().any(uses_data)
+        }
+        Value::Array(items) => items.iter().any(uses_data),
+        _ => false,
+    }
+}
+
+/// All the string values in a specification.
+fn strings(spec: &Value) -> Vec<&str> {
+    let mut found = Vec::new();
+    let mut pending = vec![spec];
+    while let Some(value) = pending.pop() {
+        match value {
+            Value::String(s) => found.push(s.as_str()),
+            Value::Array(items) => pending.extend(items),
+            Value::Object(map) => pending.extend(map.values()),
+            _ => {}
+        }
+    }
+    found
+}
+
+/// Whether a specification may use columns it does not name: a Vega specification,
+/// one with transforms, or one with tooltips showing whole records.
+fn uses_unnamed_columns(spec: &Value) -> bool {
+    if Mode::detect(spec) == Mode::Vega {
+        return true;
+    }
+    let mut pending = vec![spec];
+    while let Some(value) = pending.pop() {
+        match value {
+            Value::Array(items) => pending.extend(items),
+            Value::Object(map) => {
+                let tooltip = match map.get("tooltip") {
+                    Some(Value::Bool(true)) => true,
+                    Some(Value::Object(tooltip)) => tooltip.contains_key("content"),
+                    _ => false,
+                };
+                if tooltip || map.contains_key("transform") {
+                    return true;
+                }
+                pending.extend(map.values());
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// The indices of the `columns` that a specification mentions, either as a field
+/// name or path, or as a `datum` member in an expression.
+/// Returns `None` if none of the columns are mentioned, or if the specification
+/// may use columns it does not name.
+pub fn referenced_columns(spec: &Value, columns: &[&str]) -> Option<Vec<usize>> {
+    if uses_unnamed_columns(spec) {
+        return None;
+    }
+    let strings = strings(spec);
+    let selected = columns
+        .iter()
+        .enumerate()
+        .filter(|(_, column)| {
+            let path = format!("{column}.");
+            let member = format!("datum.{column}");
+            let quoted = [format!("datum['{column}']"), format!("datum[\"{column}\"]")];
+            strings.iter().any(|s| {
+                *s == **column
+                    || s.starts_with(&path)
+                    || s.contains(&member)
+                    || quoted.iter().any(|q| s.contains(q.as_str()))
+            })
+        })
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    if selected.is_empty() {
+        None
+    } else {
+        Some(selected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const COLUMNS: &[&str] = &["a", "b", "c", "d"];
+
+    fn patched(spec: Value, patches: &[&str]) -> Result<Value, String> {
+        let mut spec = spec;
+        for patch in patches {
+            patch.parse::<Patch>()?.apply(&mut spec)?;
+        }
+        Ok(spec)
+    }
+
+    #[test]
+    fn formats() {
+        let spec = json!({ "mark": "bar", "width": 300 });
+        let parsed =
+            |path: &str, text: &str| to_json(Path::new(path), text.to_string()).map(|t| parse(&t));
+        let yaml = "mark: bar\nwidth: 300\n";
+        let json5 = "{mark: 'bar', width: 300, /* wide */}";
+        assert_eq!(parsed("spec.yaml", yaml), Ok(spec.clone()));
+        assert_eq!(parsed("SPEC.YML", yaml), Ok(spec.clone()));
+        assert_eq!(parsed("spec.json5", json5), Ok(spec.clone()));
+        assert_eq!(parsed("spec.json", &spec.to_string()), Ok(spec.clone()));
+        // other files are not converted
+        assert_eq!(
+            to_json(Path::new("spec.vl"), yaml.to_string()),
+            Ok(yaml.to_string())
+        );
+        assert!(parsed("spec.yaml", "mark: [bar")
+            .unwrap_err()
+            .contains("not valid YAML"));
+        assert!(parsed("spec.json5", "{mark:")
+            .unwrap_err()
+            .contains("not valid JSON5"));
+    }
+
+    #[test]
+    fn stdin_formats() {
+        let spec = json!({ "mark": "bar", "width": 300 });
+        let stdin = |text: &str| to_json(Path::new("-"), text.to_string()).map(|t| parse(&t));
+        let json = r#"{"mark":"bar","width":300}"#;
+        assert_eq!(
+            to_json(Path::new("-"), json.to_string()),
+            Ok(json.to_string())
+        );
+        assert_eq!(stdin("{mark: 'bar', width: 300}"), Ok(spec.clone()));
+        assert_eq!(stdin("mark: bar\nwidth: 300"), Ok(spec));
+        assert!(stdin("mark: [bar").unwrap_err().contains("not valid YAML"));
+    }
+
+    #[test]
+    fn paths() {
+        let manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+        assert!(is_path(manifest));
+        assert!(!is_path(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/missing.json"
+        )));
+        assert!(!is_path(env!("CARGO_MANIFEST_DIR")));
+        assert!(!is_path(r#"{"mark": "bar"}"#));
+        assert!(!is_path(r#"  {"mark": "bar"}"#));
+        assert!(is_yaml(Path::new("spec.Yml")));
+        assert!(!is_yaml(Path::new("spec.json")));
+    }
+
+    #[test]
+    fn patch() {
+        let spec = json!({ "mark": "bar", "encoding": { "y": { "field": "a" } } });
+        assert_eq!(
+            patched(
+                spec,
+                &[
+                    "encoding.y.scale.type=log",
+                    "/encoding/x~1y/field=b",
+                    "mark.tooltip=true",
+                    "width=300",
+                    "title=a=b",
+                ]
+            ),
+            Ok(json!({
+                "mark": { "type": "bar", "tooltip": true },
+                "encoding": {
+                    "y": { "field": "a", "scale": { "type": "log" } },
+                    "x/y": { "field": "b" },
+                },
+                "width": 300,
+                "title": "a=b",
+            }))
+        );
+    }
+
+    #[test]
+    fn patch_arrays() {
+        let spec = json!({ "layer": [{ "mark": "line" }] });
+        assert_eq!(
+            patched(
+                spec.clone(),
+                &["layer.0.mark.point=true", "/layer/-={\"mark\": \"rule\"}"]
+            ),
+            Ok(
+                json!({ "layer": [{ "mark": { "type": "line", "point": true } }, { "mark": "rule" }] })
+            )
+        );
+        assert_eq!(
+            patched(spec.clone(), &["layer.1.mark=bar"]),
+            Err("unable to set layer.1.mark: 1 is not an index of layer".to_string())
+        );
+        assert_eq!(
+            patched(spec, &["layer.0.mark.type.x=1"]),
+            Err("unable to set layer.0.mark.type.x: type is not an object".to_string())
+        );
+        assert!("=1".parse::<Patch>().is_err());
+        assert!("width".parse::<Patch>().is_err());
+    }
+
+    #[test]
+    fn patch_new_arrays() {
+        assert_eq!(
+            patched(json!({}), &["layer.-=1", "/layer/-/mark=rule"]),
+            Ok(json!({ "layer": [1, { "mark": "rule" }] }))
+        );
+        assert_eq!(
+            patched(json!({}), &["params.0.name=brush"]),
+            Err("unable to set params.0.name: 0 is not an index of params".to_string())
+        );
+    }
+
+    #[test]
+    fn patch_empty_keys() {
+        for patch in ["/=1", "//a=1", "a..b=1", ".a=1", "a.=1"] {
+            assert_eq!(
+                patch.parse::<Patch>().unwrap_err(),
+                format!("expected a path with no empty keys, found '{patch}'")
+            );
+        }
+    }
+
+    #[test]
+    fn based_urls() {
+        let mut spec = json!({
+            "data": { "url": "/data" },
+            "layer": [
+                { "data": { "url": "/data/sites" } },
+                { "data": { "url": "https://example.com/data.csv" } },
+                { "data": { "url": "//example.com/data.csv" } },
+                { "data": { "url": "cars.json" } },
+            ],
+        });
+        base_urls(&mut spec, "/view/1");
+        assert_eq!(
+            spec,
+            json!({
+                "data": { "url": "/view/1/data" },
+                "layer": [
+                    { "data": { "url": "/view/1/data/sites" } },
+                    { "data": { "url": "https://example.com/data.csv" } },
+                    { "data": { "url": "//example.com/data.csv" } },
+                    { "data": { "url": "cars.json" } },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn merged_config() {
+        let mut spec = json!({
+            "mark": "bar",
+            "config": { "font": "serif", "axis": { "grid": false, "labelColor": "red" } },
+        });
+        let config = json!({ "axis": { "grid": true }, "view": { "stroke": null } });
+        merge_config(&mut spec, &config);
+        assert_eq!(
+            spec,
+            json!({
+                "mark": "bar",
+                "config": {
+                    "font": "serif",
+                    "axis": { "grid": true, "labelColor": "red" },
+                    "view": { "stroke": null },
+                },
+            })
+        );
+
+        let mut spec = json!({ "mark": "bar" });
+        merge_config(&mut spec, &config);
+        assert_eq!(spec, json!({ "mark": "bar", "config": config }));
+    }
+
+    #[test]
+    fn linked_datasets() {
+        let mut spec = json!({
+            "data": { "name": "sites" },
+            "layer": [
+                { "data": { "name": "stations", "format": { "type": "json" } } },
+                { "data": { "name": "other" } },
+                { "mark": "rule" },
+            ],
+            "vconcat": [{ "data": { "name": "sites" } }],
+        });
+        link_datasets(&mut spec, &["sites", "stations"]);
+        assert_eq!(
+            spec,
+            json!({
+                "data": { "url": "/data/sites" },
+                "layer": [
+                    { "data": { "format": { "type": "json" }, "url": "/data/stations" } },
+                    { "data": { "name": "other" } },
+                    { "mark": "rule" },
+                ],
+                "vconcat": [{ "data": { "url": "/data/sites" } }],
+            })
+        );
+    }
+
+    #[test]
+    fn modes() {
+        let vega = json!({ "$schema": "https://vega.github.io/schema/vega/v5.json" });
+        assert_eq!(Mode::detect(&vega), Mode::Vega);
+        let lite = json!({ "$schema": "https://vega.github.io/schema/vega-lite/v5.json" });
+        assert_eq!(Mode::detect(&lite), Mode::VegaLite);
+        assert_eq!(Mode::detect(&json!({ "mark": "bar" })), Mode::VegaLite);
+        assert_eq!(Mode::Vega.name(), "vega");
+        assert_eq!(Mode::VegaLite.name(), "vega-lite");
+    }
+
+    #[test]
+    fn linked_vega_data() {
+        let mut spec = json!({
+            "$schema": "https://vega.github.io/schema/vega/v5.json",
+            "data": [
+                { "name": "table" },
+                { "name": "sites" },
+                { "name": "other" },
+                { "name": "cars", "url": "cars.json" },
+                { "name": "points", "values": [] },
+                { "name": "summary", "source": "table" },
+            ],
+        });
+        link_vega_data(&mut spec, &["sites"]);
+        assert_eq!(
+            spec["data"],
+            json!([
+                { "name": "table", "url": "/data" },
+                { "name": "sites", "url": "/data/sites" },
+                { "name": "other" },
+                { "name": "cars", "url": "cars.json" },
+                { "name": "points", "values": [] },
+                { "name": "summary", "source": "table" },
+            ])
+        );
+
+        // the data is loaded by an entry already
+        let data = json!([{ "name": "table" }, { "name": "raw", "url": "/data" }]);
+        let mut spec = json!({ "data": data });
+        link_vega_data(&mut spec, &[]);
+        assert_eq!(spec["data"], data);
+
+        // a vega-lite spec has no data array to link
+        let mut spec = json!({ "data": { "name": "table" }, "mark": "bar" });
+        let lite = spec.clone();
+        link_vega_data(&mut spec, &["table"]);
+        assert_eq!(spec, lite);
+    }
+
+    #[test]
+    fn projection() {
+        let spec = json!({
+            "mark": "point",
+            "encoding": {
+                "x": { "field": "a" },
+                "y": { "field": "b.value" },
+                "color": { "condition": { "test": "datum['c'] > 0", "value": "red" } },
+            },
+        });
+        assert_eq!(referenced_columns(&spec, COLUMNS), Some(vec![0, 1, 2]));
+        assert_eq!(
+            referenced_columns(&json!({ "mark": "point" }), COLUMNS),
+            None
+        );
+    }
+
+    #[test]
+    fn unnamed_columns() {
+        let specs = [
+            json!({ "mark": { "type": "bar", "tooltip": true }, "encoding": { "x": { "field": "a" } } }),
+            json!({ "mark": { "type": "bar", "tooltip": { "content": "data" } }, "encoding": { "x": { "field": "a" } } }),
+            json!({ "transform": [{ "fold": ["b", "c"] }], "encoding": { "x": { "field": "a" } } }),
+            json!({ "layer": [{ "transform": [{ "calculate": "datum.b", "as": "e" }], "encoding": { "x": { "field": "a" } } }] }),
+            json!({ "$schema": "https://vega.github.io/schema/vega/v5.json", "marks": [{ "encode": { "update": { "x": { "field": "a" } } } }] }),
+        ];
+        for spec in specs {
+            assert_eq!(referenced_columns(&spec, COLUMNS), None, "{spec}");
+        }
+    }
+}
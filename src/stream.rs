@@ -0,0 +1,152 @@
+use serde_json::Value;
+use std::{
+    collections::VecDeque,
+    io::{stdin, BufRead},
+    sync::mpsc::{channel, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The name of the dataset that receives streamed records.
+pub const NAME: &str = "stream";
+
+const BATCH: Duration = Duration::from_millis(100);
+
+/// Read newline delimited JSON records from stdin on a background thread,
+/// delivering those that arrive close together as a batch.
+pub fn spawn(deliver: impl Fn(Vec<Value>) + Send + 'static) {
+    let (sender, receiver) = channel::<Value>();
+    thread::spawn(move || {
+        for (i, line) in stdin().lock().lines().enumerate() {
+            let line = match line {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => line,
+                Err(e) => {
+                    eprintln!("unable to read stdin: {e}");
+                    break;
+                }
+            };
+            match serde_json::from_str(&line) {
+                Ok(record) => {
+                    if sender.send(record).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => eprintln!("invalid JSON record on line {}: {e}", i + 1),
+            }
+        }
+    });
+    thread::spawn(move || {
+        while let Ok(first) = receiver.recv() {
+            let mut batch = vec![first];
+            let deadline = Instant::now() + BATCH;
+            loop {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                match receiver.recv_timeout(timeout) {
+                    Ok(record) => batch.push(record),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            deliver(batch);
+        }
+    });
+}
+
+/// The streamed records retained for the page, limited to a sliding window.
+#[derive(Debug)]
+pub struct Buffer {
+    rows: VecDeque<Value>,
+    window: Option<usize>,
+    ready: bool,
+}
+
+impl Buffer {
+    pub fn new(window: Option<usize>) -> Self {
+        Self {
+            rows: VecDeque::new(),
+            window,
+            ready: false,
+        }
+    }
+
+    /// Retain a batch of records, returning the script that inserts
+    /// them into the view if the page is ready for it.
+    pub fn push(&mut self, mut batch: Vec<Value>) -> Option<String> {
+        if let Some(window) = self.window {
+            let excess = batch.len().saturating_sub(window);
+            batch.drain(..excess);
+        }
+        let script = self.ready.then(|| self.insert_script(&batch));
+        self.rows.extend(batch);
+        if let Some(window) = self.window {
+            let excess = self.rows.len().saturating_sub(window);
+            self.rows.drain(..excess);
+        }
+        script
+    }
+
+    /// The page has (re)loaded, so all retained records must be inserted.
+    pub fn ready(&mut self) -> String {
+        self.ready = true;
+        let rows = self.rows.iter().cloned().collect::<Vec<_>>();
+        self.insert_script(&rows)
+    }
+
+    fn insert_script(&self, rows: &[Value]) -> String {
+        let name = Value::from(NAME);
+        let rows = Value::from(rows.to_vec());
+        let window = self.window.map_or(Value::Null, Value::from);
+        format!("vegaInsert({name}, {rows}, {window})")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rows(range: std::ops::Range<i32>) -> Vec<Value> {
+        range.map(|i| json!({ "i": i })).collect()
+    }
+
+    #[test]
+    fn before_ready() {
+        let mut buffer = Buffer::new(None);
+        assert_eq!(buffer.push(rows(0..2)), None);
+        assert_eq!(buffer.push(rows(2..3)), None);
+        assert_eq!(
+            buffer.ready(),
+            r#"vegaInsert("stream", [{"i":0},{"i":1},{"i":2}], null)"#
+        );
+        assert_eq!(
+            buffer.push(rows(3..4)).as_deref(),
+            Some(r#"vegaInsert("stream", [{"i":3}], null)"#)
+        );
+        // a reloaded page is given every record again
+        assert_eq!(
+            buffer.ready(),
+            r#"vegaInsert("stream", [{"i":0},{"i":1},{"i":2},{"i":3}], null)"#
+        );
+    }
+
+    #[test]
+    fn window() {
+        let mut buffer = Buffer::new(Some(3));
+        buffer.push(rows(0..2));
+        buffer.push(rows(2..4));
+        assert_eq!(
+            buffer.ready(),
+            r#"vegaInsert("stream", [{"i":1},{"i":2},{"i":3}], 3)"#
+        );
+        // only the last records of a batch larger than the window are inserted
+        assert_eq!(
+            buffer.push(rows(4..9)).as_deref(),
+            Some(r#"vegaInsert("stream", [{"i":6},{"i":7},{"i":8}], 3)"#)
+        );
+        assert_eq!(
+            buffer.ready(),
+            r#"vegaInsert("stream", [{"i":6},{"i":7},{"i":8}], 3)"#
+        );
+    }
+}
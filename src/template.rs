@@ -0,0 +1,255 @@
+use serde_json::Value;
+use std::{env, str::FromStr};
+
+/// A template variable, given on the command line as `name=value`.
+#[derive(Clone, Debug)]
+pub struct Var {
+    pub name: String,
+    pub value: String,
+}
+
+impl FromStr for Var {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((name, value)) if is_name(name) => Ok(Self {
+                name: name.to_string(),
+                value: value.to_string(),
+            }),
+            _ => Err(format!("expected name=value, found '{s}'")),
+        }
+    }
+}
+
+/// Replace each `{{name}}` placeholder in a spec with the value of the last
+/// variable of that name, and each `{{env.NAME}}` with the environment variable.
+/// Values that land inside a quoted string are escaped for it, by YAML rules if
+/// `yaml` is set and otherwise by JSON and JSON5 rules.  Elsewhere they are inserted
+/// as they are.  Placeholders that can't be resolved are an error, except those with
+/// a `.` that are not for the environment, such as `{{datum.x}}` in a Vega text mark,
+/// which are left as they are.
+pub fn fill(text: &str, vars: &[Var], yaml: bool) -> Result<String, String> {
+    let mut filled = String::with_capacity(text.len());
+    let mut unresolved = Vec::<&str>::new();
+    let mut scanner = Scanner::new(yaml);
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let placeholder = rest[start + 2..]
+            .find("}}")
+            .map(|end| (rest[start + 2..start + 2 + end].trim(), start + 2 + end + 2))
+            .filter(|(name, _)| is_name(name));
+        let Some((name, end)) = placeholder else {
+            filled.push_str(&rest[..start + 2]);
+            scanner.scan(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            continue;
+        };
+        let value = lookup(name, vars);
+        if value.is_none() && name.contains('.') && !name.starts_with("env.") {
+            filled.push_str(&rest[..end]);
+            scanner.scan(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        filled.push_str(&rest[..start]);
+        scanner.scan(&rest[..start]);
+        match value {
+            Some(value) => filled.push_str(&scanner.escape(&value)),
+            None if !unresolved.contains(&name) => unresolved.push(name),
+            None => {}
+        }
+        scanner.after_value();
+        rest = &rest[end..];
+    }
+    filled.push_str(rest);
+    if unresolved.is_empty() {
+        Ok(filled)
+    } else {
+        let names = unresolved
+            .iter()
+            .map(|name| format!("{{{{{name}}}}}"))
+            .collect::<Vec<_>>();
+        Err(format!(
+            "the spec has placeholders with no --var or environment variable: {}",
+            names.join(", ")
+        ))
+    }
+}
+
+/// Follows the text of a spec far enough to tell whether a placeholder is
+/// inside a string quoted with `"` or `'`.
+struct Scanner {
+    yaml: bool,
+    quote: Option<char>,
+    escaped: bool,
+    comment: bool,
+    /// The last character on the line that is not whitespace, if any.
+    after: Option<char>,
+    /// Whether the last character was whitespace, or there was none.
+    space: bool,
+}
+
+impl Scanner {
+    fn new(yaml: bool) -> Self {
+        Self {
+            yaml,
+            quote: None,
+            escaped: false,
+            comment: false,
+            after: None,
+            space: true,
+        }
+    }
+
+    fn scan(&mut self, text: &str) {
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\n' {
+                self.comment = false;
+                self.after = None;
+                if self.quote.is_some() {
+                    continue;
+                }
+            }
+            match self.quote {
+                _ if self.comment => {}
+                Some(_) if self.escaped => self.escaped = false,
+                Some('\'') if self.yaml && c == '\'' && chars.peek() == Some(&'\'') => {
+                    chars.next();
+                }
+                Some(quote) if c == quote => self.quote = None,
+                Some('\'') if self.yaml => {}
+                Some(_) => self.escaped = c == '\\',
+                // a quote only starts a string where a value or key can start,
+                // so that the apostrophe in an unquoted YAML string is not taken for one
+                None if matches!(c, '"' | '\'') && self.opens() => self.quote = Some(c),
+                None if self.yaml && c == '#' && self.space => self.comment = true,
+                None if !self.yaml && c == '/' && chars.peek() == Some(&'/') => self.comment = true,
+                None => {}
+            }
+            if !c.is_whitespace() {
+                self.after = Some(c);
+            }
+            self.space = c.is_whitespace();
+        }
+    }
+
+    /// Whether a quote here would start a string.
+    fn opens(&self) -> bool {
+        self.after
+            .is_none_or(|c| matches!(c, '[' | '{' | ':' | ',' | '-' | '?'))
+    }
+
+    /// Note that a value has been inserted where a string could not start.
+    fn after_value(&mut self) {
+        if self.quote.is_none() {
+            self.after = Some('}');
+        }
+        self.space = false;
+    }
+
+    /// A value escaped for the string the scanner is in, if any.
+    fn escape(&self, value: &str) -> String {
+        let json = Value::from(value).to_string();
+        let json = &json[1..json.len() - 1];
+        match self.quote {
+            Some('\'') if self.yaml => value.replace('\'', "''"),
+            Some('\'') => json.replace('\'', "\\'"),
+            Some(_) => json.to_string(),
+            None => value.to_string(),
+        }
+    }
+}
+
+/// The value of the last variable of the name, or for `env.NAME` of the environment
+/// variable.
+fn lookup(name: &str, vars: &[Var]) -> Option<String> {
+    vars.iter()
+        .rev()
+        .find(|var| var.name == name)
+        .map(|var| var.value.clone())
+        .or_else(|| env::var(name.strip_prefix("env.")?).ok())
+}
+
+/// Whether a placeholder name is valid: letters, digits, `_`, `-` and `.`.
+fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str, value: &str) -> Var {
+        Var {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn quoting() {
+        let vars = [var("title", r#"say "hi" \ bye's"#), var("width", "300")];
+        let json = fill(
+            r#"{"title": "{{title}}", "width": {{width}}}"#,
+            &vars,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&json).unwrap(),
+            serde_json::json!({"title": r#"say "hi" \ bye's"#, "width": 300})
+        );
+        let json5 = fill("{title: '{{title}}'} // it's", &vars, false).unwrap();
+        assert_eq!(json5, r#"{title: 'say \"hi\" \\ bye\'s'} // it's"#);
+        let yaml = fill("title: '{{title}}'\nnote: it's {{ width }}\n", &vars, true).unwrap();
+        assert_eq!(yaml, "title: 'say \"hi\" \\ bye''s'\nnote: it's 300\n");
+    }
+
+    #[test]
+    fn missing() {
+        let error = fill(
+            r#"{"a": "{{a}}", "b": "{{b}} {{a}}"}"#,
+            &[var("b", "1")],
+            false,
+        );
+        assert_eq!(
+            error.unwrap_err(),
+            "the spec has placeholders with no --var or environment variable: {{a}}"
+        );
+        assert_eq!(
+            fill("{{ not a name }}", &[], false).unwrap(),
+            "{{ not a name }}"
+        );
+    }
+
+    #[test]
+    fn environment() {
+        let name = env!("CARGO_PKG_NAME");
+        let text = r#"{"title": "{{env.CARGO_PKG_NAME}}"}"#;
+        assert_eq!(
+            fill(text, &[], false).unwrap(),
+            format!(r#"{{"title": "{name}"}}"#)
+        );
+        let vars = [var("env.CARGO_PKG_NAME", "given")];
+        assert_eq!(fill(text, &vars, false).unwrap(), r#"{"title": "given"}"#);
+        // the environment is only used when asked for
+        assert!(fill("{{CARGO_PKG_NAME}}", &[], false).is_err());
+        assert!(fill("{{env.VEGA_VIEW_UNSET}}", &[], false).is_err());
+    }
+
+    #[test]
+    fn expressions() {
+        let text = r#"{"text": "{{ datum.x }} of {{n}}", "expr": "'{{datum.y}}'"}"#;
+        assert_eq!(
+            fill(text, &[var("n", "it's")], false).unwrap(),
+            r#"{"text": "{{ datum.x }} of it's", "expr": "'{{datum.y}}'"}"#
+        );
+        let vars = [var("datum.x", "1")];
+        assert_eq!(fill("{{datum.x}}", &vars, false).unwrap(), "1");
+    }
+}
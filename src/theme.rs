@@ -0,0 +1,94 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// The vega-themes presets known to vega-embed.
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Dark or light, following the window theme of the desktop.
+    Auto,
+    /// The default vega look.
+    Light,
+    Dark,
+    Excel,
+    Fivethirtyeight,
+    Ggplot2,
+    Googlecharts,
+    Latimes,
+    Powerbi,
+    Quartz,
+    Urbaninstitute,
+    Vox,
+    Carbonwhite,
+    Carbong10,
+    Carbong90,
+    Carbong100,
+}
+
+impl Theme {
+    /// The theme for a window of the given theme, when following it.
+    pub fn resolve(self, window: tao::window::Theme) -> Self {
+        match (self, window) {
+            (Self::Auto, tao::window::Theme::Dark) => Self::Dark,
+            (Self::Auto, _) => Self::Light,
+            (theme, _) => theme,
+        }
+    }
+
+    /// Whether the theme has a dark background.
+    pub fn is_dark(self) -> bool {
+        matches!(self, Self::Dark | Self::Carbong90 | Self::Carbong100)
+    }
+
+    /// The name vega-embed knows the theme by, if any.
+    pub fn name(self) -> Option<&'static str> {
+        let name = match self {
+            Self::Auto | Self::Light => return None,
+            Self::Dark => "dark",
+            Self::Excel => "excel",
+            Self::Fivethirtyeight => "fivethirtyeight",
+            Self::Ggplot2 => "ggplot2",
+            Self::Googlecharts => "googlecharts",
+            Self::Latimes => "latimes",
+            Self::Powerbi => "powerbi",
+            Self::Quartz => "quartz",
+            Self::Urbaninstitute => "urbaninstitute",
+            Self::Vox => "vox",
+            Self::Carbonwhite => "carbonwhite",
+            Self::Carbong10 => "carbong10",
+            Self::Carbong90 => "carbong90",
+            Self::Carbong100 => "carbong100",
+        };
+        Some(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve() {
+        assert_eq!(Theme::Auto.resolve(tao::window::Theme::Dark), Theme::Dark);
+        assert_eq!(Theme::Auto.resolve(tao::window::Theme::Light), Theme::Light);
+        assert_eq!(Theme::Vox.resolve(tao::window::Theme::Dark), Theme::Vox);
+        assert_eq!(Theme::Light.resolve(tao::window::Theme::Dark), Theme::Light);
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(Theme::Auto.name(), None);
+        assert_eq!(Theme::Light.name(), None);
+        // the other themes are named as on the command line
+        for theme in Theme::value_variants() {
+            if let Some(name) = theme.name() {
+                assert_eq!(
+                    Some(name),
+                    theme.to_possible_value().as_ref().map(|v| v.get_name())
+                );
+            }
+        }
+        assert!(Theme::Carbong100.is_dark());
+        assert!(!Theme::Carbong10.is_dark());
+    }
+}
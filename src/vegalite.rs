@@ -0,0 +1,656 @@
+//! Typed vega-lite specifications, built in Rust rather than as JSON.
+//!
+//! ```
+//! use vega_view::vegalite::{Aggregate, Channel, Chart, Encoding, FieldType, Mark, MarkType};
+//!
+//! let chart = Chart::new(Mark::new(MarkType::Bar).tooltip(true)).encoding(
+//!     Encoding::new()
+//!         .x(Channel::field("category", FieldType::Nominal))
+//!         .y(Channel::field("price", FieldType::Quantitative).aggregate(Aggregate::Mean)),
+//! );
+//! assert_eq!(chart.to_value()["encoding"]["y"]["aggregate"], "mean");
+//! ```
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// The vega-lite schema the specifications are written against.
+pub const SCHEMA: &str = "https://vega.github.io/schema/vega-lite/v5.json";
+
+/// A vega-lite specification: a single view, a layered view or a faceted view.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Chart {
+    #[serde(rename = "$schema", skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Data>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transform: Vec<Transform>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mark: Option<Mark>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<Size>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<Size>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<Encoding>,
+    /// The views drawn over each other, for a layered chart.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layer: Vec<Chart>,
+    /// The fields splitting the data into rows and columns, for a faceted chart.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facet: Option<Facet>,
+    /// The view repeated for each facet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spec: Option<Box<Chart>>,
+}
+
+impl Chart {
+    /// A single view drawing the mark.
+    pub fn new(mark: Mark) -> Self {
+        Self {
+            schema: Some(SCHEMA.to_string()),
+            mark: Some(mark),
+            ..Self::default()
+        }
+    }
+
+    /// Views drawn over each other, sharing their axes.
+    /// The layers should not have their own `$schema`.
+    pub fn layered(layers: impl IntoIterator<Item = Chart>) -> Self {
+        Self {
+            schema: Some(SCHEMA.to_string()),
+            layer: layers.into_iter().map(Chart::inner).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// A view repeated for each row and column of the facet.
+    pub fn faceted(facet: Facet, spec: Chart) -> Self {
+        Self {
+            schema: Some(SCHEMA.to_string()),
+            facet: Some(facet),
+            spec: Some(Box::new(spec.inner())),
+            ..Self::default()
+        }
+    }
+
+    /// The chart as part of another, without its `$schema`.
+    fn inner(self) -> Self {
+        Self {
+            schema: None,
+            ..self
+        }
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn data(mut self, data: Data) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    /// Add a transform, applied after those already added.
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.transform.push(transform);
+        self
+    }
+
+    pub fn width(mut self, width: Size) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    pub fn height(mut self, height: Size) -> Self {
+        self.height = Some(height);
+        self
+    }
+
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// The chart as vega-lite JSON.
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).expect("a chart is always valid JSON")
+    }
+}
+
+/// Where a view's data comes from.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Data {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub values: Option<Value>,
+}
+
+impl Data {
+    /// Data loaded from a URL, such as `/data` for the data served by the view.
+    pub fn url(url: impl Into<String>) -> Self {
+        Self {
+            url: Some(url.into()),
+            ..Self::default()
+        }
+    }
+
+    /// A named data source, linked to a dataset of the view.
+    pub fn named(name: impl Into<String>) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Data given in the specification.
+    pub fn values(values: Value) -> Self {
+        Self {
+            values: Some(values),
+            ..Self::default()
+        }
+    }
+}
+
+/// The width or height of a view.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(into = "Value", try_from = "Value")]
+pub enum Size {
+    /// Fill the width or height of the page.
+    Container,
+    Pixels(u32),
+}
+
+impl From<Size> for Value {
+    fn from(size: Size) -> Self {
+        match size {
+            Size::Container => "container".into(),
+            Size::Pixels(pixels) => pixels.into(),
+        }
+    }
+}
+
+impl TryFrom<Value> for Size {
+    type Error = String;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(s) if s == "container" => Ok(Self::Container),
+            Value::Number(n) => n
+                .as_u64()
+                .and_then(|n| u32::try_from(n).ok())
+                .map(Self::Pixels)
+                .ok_or_else(|| format!("{n} is not a size in pixels")),
+            _ => Err(format!("expected \"container\" or pixels, found {value}")),
+        }
+    }
+}
+
+/// The graphical mark drawn for each data row, or for each series.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Mark {
+    #[serde(rename = "type")]
+    pub kind: MarkType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tooltip: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub point: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opacity: Option<f64>,
+}
+
+impl Mark {
+    pub fn new(kind: MarkType) -> Self {
+        Self {
+            kind,
+            tooltip: None,
+            point: None,
+            color: None,
+            opacity: None,
+        }
+    }
+
+    /// Show the data of the mark when the pointer is over it.
+    pub fn tooltip(mut self, tooltip: bool) -> Self {
+        self.tooltip = Some(tooltip);
+        self
+    }
+
+    /// Draw points on a line or area mark.
+    pub fn point(mut self, point: bool) -> Self {
+        self.point = Some(point);
+        self
+    }
+
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+
+    pub fn opacity(mut self, opacity: f64) -> Self {
+        self.opacity = Some(opacity);
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum MarkType {
+    Arc,
+    Area,
+    Bar,
+    Boxplot,
+    Circle,
+    Errorband,
+    Errorbar,
+    Geoshape,
+    Image,
+    Line,
+    Point,
+    Rect,
+    Rule,
+    Square,
+    Text,
+    Tick,
+    Trail,
+}
+
+/// How data fields map to the properties of the marks.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Encoding {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<Channel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<Channel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x2: Option<Channel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y2: Option<Channel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theta: Option<Channel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<Channel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shape: Option<Channel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<Channel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opacity: Option<Channel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<Channel>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tooltip: Vec<Channel>,
+    /// The facet row, for a view faceted by encoding.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub row: Option<Channel>,
+    /// The facet column, for a view faceted by encoding.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<Channel>,
+}
+
+impl Encoding {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn x(mut self, channel: Channel) -> Self {
+        self.x = Some(channel);
+        self
+    }
+
+    pub fn y(mut self, channel: Channel) -> Self {
+        self.y = Some(channel);
+        self
+    }
+
+    pub fn x2(mut self, channel: Channel) -> Self {
+        self.x2 = Some(channel);
+        self
+    }
+
+    pub fn y2(mut self, channel: Channel) -> Self {
+        self.y2 = Some(channel);
+        self
+    }
+
+    pub fn theta(mut self, channel: Channel) -> Self {
+        self.theta = Some(channel);
+        self
+    }
+
+    pub fn color(mut self, channel: Channel) -> Self {
+        self.color = Some(channel);
+        self
+    }
+
+    pub fn shape(mut self, channel: Channel) -> Self {
+        self.shape = Some(channel);
+        self
+    }
+
+    pub fn size(mut self, channel: Channel) -> Self {
+        self.size = Some(channel);
+        self
+    }
+
+    pub fn opacity(mut self, channel: Channel) -> Self {
+        self.opacity = Some(channel);
+        self
+    }
+
+    pub fn text(mut self, channel: Channel) -> Self {
+        self.text = Some(channel);
+        self
+    }
+
+    /// Add a field to the tooltip.
+    pub fn tooltip(mut self, channel: Channel) -> Self {
+        self.tooltip.push(channel);
+        self
+    }
+
+    pub fn row(mut self, channel: Channel) -> Self {
+        self.row = Some(channel);
+        self
+    }
+
+    pub fn column(mut self, channel: Channel) -> Self {
+        self.column = Some(channel);
+        self
+    }
+}
+
+/// A field, or a constant, for one property of the marks.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Channel {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aggregate: Option<Aggregate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<FieldType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bin: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_unit: Option<TimeUnit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub axis: Option<Axis>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<Scale>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stack: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+}
+
+impl Channel {
+    /// A data field, interpreted as the given type.
+    pub fn field(field: impl Into<String>, kind: FieldType) -> Self {
+        Self {
+            field: Some(field.into()),
+            kind: Some(kind),
+            ..Self::default()
+        }
+    }
+
+    /// The number of data rows, which needs no field.
+    pub fn count() -> Self {
+        Self {
+            aggregate: Some(Aggregate::Count),
+            kind: Some(FieldType::Quantitative),
+            ..Self::default()
+        }
+    }
+
+    /// The same value for every mark.
+    pub fn value(value: impl Into<Value>) -> Self {
+        Self {
+            value: Some(value.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Combine the values of the field over the rows for each mark.
+    pub fn aggregate(mut self, aggregate: Aggregate) -> Self {
+        self.aggregate = Some(aggregate);
+        self
+    }
+
+    /// Group the values of the field into bins.
+    pub fn bin(mut self, bin: bool) -> Self {
+        self.bin = Some(bin);
+        self
+    }
+
+    /// Round the times of the field down to the unit.
+    pub fn time_unit(mut self, unit: TimeUnit) -> Self {
+        self.time_unit = Some(unit);
+        self
+    }
+
+    /// The title for the axis or legend.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn axis(mut self, axis: Axis) -> Self {
+        self.axis = Some(axis);
+        self
+    }
+
+    pub fn scale(mut self, scale: Scale) -> Self {
+        self.scale = Some(scale);
+        self
+    }
+
+    /// Whether to stack marks sharing a position.
+    pub fn stack(mut self, stack: bool) -> Self {
+        self.stack = Some(stack);
+        self
+    }
+}
+
+/// How the values of a field are interpreted.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    Quantitative,
+    Temporal,
+    Ordinal,
+    Nominal,
+}
+
+/// How the values of a field are combined for a mark.
+#[derive(Serialize, Deserialize, ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregate {
+    Count,
+    Valid,
+    Values,
+    Missing,
+    Distinct,
+    Sum,
+    Product,
+    Mean,
+    Average,
+    Variance,
+    Variancep,
+    Stdev,
+    Stdevp,
+    Stderr,
+    Median,
+    Q1,
+    Q3,
+    Ci0,
+    Ci1,
+    Min,
+    Max,
+}
+
+impl fmt::Display for Aggregate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_possible_value() {
+            Some(value) => f.write_str(value.get_name()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The unit times are rounded down to.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeUnit {
+    Year,
+    Quarter,
+    Month,
+    Week,
+    Date,
+    Day,
+    Hours,
+    Minutes,
+    Seconds,
+    YearMonth,
+    YearMonthDate,
+    MonthDate,
+    HoursMinutes,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Axis {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grid: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label_angle: Option<f64>,
+}
+
+impl Axis {
+    /// An axis with a title.
+    pub fn titled(title: impl Into<String>) -> Self {
+        Self {
+            title: Some(title.into()),
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Scale {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ScaleType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zero: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<Vec<Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheme: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScaleType {
+    Linear,
+    Log,
+    Pow,
+    Sqrt,
+    Symlog,
+    Time,
+    Utc,
+    Ordinal,
+    Band,
+    Point,
+}
+
+/// The fields a faceted chart is split by.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Facet {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub row: Option<Channel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<Channel>,
+}
+
+impl Facet {
+    pub fn row(mut self, channel: Channel) -> Self {
+        self.row = Some(channel);
+        self
+    }
+
+    pub fn column(mut self, channel: Channel) -> Self {
+        self.column = Some(channel);
+        self
+    }
+}
+
+/// A step deriving the data of a view from its source.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Transform {
+    /// Keep the rows for which the vega expression is true.
+    Filter { filter: String },
+    /// Add a field computed by the vega expression.
+    Calculate {
+        calculate: String,
+        #[serde(rename = "as")]
+        name: String,
+    },
+    /// Summarize the rows of each group.
+    Aggregate {
+        aggregate: Vec<Aggregated>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        groupby: Vec<String>,
+    },
+    /// Turn fields into rows of key and value.
+    Fold {
+        fold: Vec<String>,
+        #[serde(rename = "as", skip_serializing_if = "Option::is_none")]
+        names: Option<[String; 2]>,
+    },
+    /// Add a field of the bin of the values of a field.
+    Bin {
+        bin: bool,
+        field: String,
+        #[serde(rename = "as")]
+        name: String,
+    },
+    /// Add a field of the times of a field, rounded down to the unit.
+    TimeUnit {
+        #[serde(rename = "timeUnit")]
+        time_unit: TimeUnit,
+        field: String,
+        #[serde(rename = "as")]
+        name: String,
+    },
+}
+
+/// One field computed by an aggregate transform.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Aggregated {
+    pub op: Aggregate,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    #[serde(rename = "as")]
+    pub name: String,
+}
@@ -0,0 +1,853 @@
+use crate::{
+    data::{self, Format},
+    export, infer, lint, schema,
+    spec::{self, Mode, Patch},
+    stream,
+    template::{self, Var},
+    theme::Theme,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+    borrow::Cow,
+    fmt, fs,
+    io::{self, stdin, Read},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::Instant,
+};
+use wry::http::{Method, Request, Response, StatusCode};
+
+pub(crate) const PAGE: &[u8] = include_bytes!("vega-page.html");
+pub(crate) const SCRIPT: &[u8] = include_bytes!("vega-all.js");
+
+/// Where the data for a view comes from.
+#[derive(Clone, Debug)]
+enum Source {
+    Stdin,
+    File(PathBuf),
+    Bytes(Vec<u8>),
+    Sqlite { db: PathBuf, query: String },
+}
+
+impl Source {
+    /// The format of the data, explicit or implied by the data file.
+    fn format(&self, format: Option<Format>) -> Format {
+        match self {
+            Self::File(path) => format.or_else(|| Format::from_path(path)),
+            _ => format,
+        }
+        .unwrap_or(Format::Json)
+    }
+}
+
+/// A problem found with the spec of a view.
+#[derive(Debug, Clone)]
+pub enum Problem {
+    Error(String),
+    Warning(String),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error(message) => write!(f, "error: {message}"),
+            Self::Warning(message) => write!(f, "warning: {message}"),
+        }
+    }
+}
+
+/// A Vega or Vega-Lite visualization and the window to show it in.
+///
+/// ```no_run
+/// use vega_view::ViewBuilder;
+/// use serde_json::json;
+///
+/// let rows = vec![json!({"a": "x", "b": 1}), json!({"a": "y", "b": 2})];
+/// let spec = json!({
+///     "data": {"url": "/data"},
+///     "mark": "bar",
+///     "encoding": {"x": {"field": "a"}, "y": {"field": "b"}},
+/// });
+/// ViewBuilder::new()
+///     .spec_value(&spec)
+///     .rows(&rows)
+///     .unwrap()
+///     .title("Example")
+///     .run()
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct ViewBuilder {
+    pub(crate) spec: Option<String>,
+    pub(crate) spec_file: Option<PathBuf>,
+    pub(crate) var: Vec<Var>,
+    pub(crate) set: Vec<Patch>,
+    pub(crate) mode: Option<Mode>,
+    pub(crate) theme: Option<Theme>,
+    pub(crate) config: Option<PathBuf>,
+    pub(crate) page: Option<PathBuf>,
+    pub(crate) script: Option<PathBuf>,
+    data: Source,
+    format: Option<Format>,
+    datasets: Vec<(String, Source)>,
+    pub(crate) stream: bool,
+    pub(crate) window: Option<usize>,
+    serve_arrow: bool,
+    pub(crate) title: Option<String>,
+    pub(crate) target: Option<String>,
+    /// The path the view is served under, when it shares a handler with other views.
+    pub(crate) base: Option<String>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) output: Option<PathBuf>,
+    strict: bool,
+    pub(crate) exit_on_error: bool,
+    pub(crate) select: bool,
+    pub(crate) watch: bool,
+    pub(crate) debug: bool,
+}
+
+impl Default for ViewBuilder {
+    fn default() -> Self {
+        Self {
+            spec: None,
+            spec_file: None,
+            var: Vec::new(),
+            set: Vec::new(),
+            mode: None,
+            theme: None,
+            config: None,
+            page: None,
+            script: None,
+            data: Source::Bytes(b"[]".to_vec()),
+            format: None,
+            datasets: Vec::new(),
+            stream: false,
+            window: None,
+            serve_arrow: false,
+            title: None,
+            target: None,
+            base: None,
+            width: 1000,
+            height: 800,
+            output: None,
+            strict: false,
+            exit_on_error: false,
+            select: false,
+            watch: false,
+            debug: false,
+        }
+    }
+}
+
+impl ViewBuilder {
+    /// A view of no data, with no spec.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The JSON text of the specification.
+    pub fn spec(mut self, spec: impl Into<String>) -> Self {
+        self.spec = Some(spec.into());
+        self.spec_file = None;
+        self
+    }
+
+    /// The specification.
+    pub fn spec_value(self, spec: &Value) -> Self {
+        self.spec(spec.to_string())
+    }
+
+    /// A JSON, YAML or JSON5 file containing the specification, or `-` for stdin.
+    pub fn spec_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.spec_file = Some(path.into());
+        self.spec = None;
+        self
+    }
+
+    /// A value for the `{{name}}` placeholders in the spec.
+    pub fn var(mut self, var: Var) -> Self {
+        self.var.push(var);
+        self
+    }
+
+    /// A property of the spec to set.
+    pub fn set(mut self, patch: Patch) -> Self {
+        self.set.push(patch);
+        self
+    }
+
+    /// The language of the spec, rather than detecting it from its `$schema`.
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// The vega-themes preset for the chart.
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = Some(theme);
+        self
+    }
+
+    /// A JSON, YAML or JSON5 file of vega config, merged into the spec's config.
+    pub fn config(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config = Some(path.into());
+        self
+    }
+
+    /// A file containing a HTML template for the page.
+    pub fn page(mut self, path: impl Into<PathBuf>) -> Self {
+        self.page = Some(path.into());
+        self
+    }
+
+    /// A file containing javascript used in the page.
+    pub fn script(mut self, path: impl Into<PathBuf>) -> Self {
+        self.script = Some(path.into());
+        self
+    }
+
+    /// Read the data to visualize from stdin, as the command line does
+    /// when no data file or database is given.
+    pub fn stdin(mut self) -> Self {
+        self.data = Source::Stdin;
+        self
+    }
+
+    /// The data to visualize, as JSON.
+    pub fn data(mut self, data: &Value) -> Self {
+        self.data = Source::Bytes(data.to_string().into_bytes());
+        self
+    }
+
+    /// The data to visualize, as rows that serialize to JSON records.
+    pub fn rows<T: Serialize>(mut self, rows: &[T]) -> serde_json::Result<Self> {
+        self.data = Source::Bytes(serde_json::to_vec(rows)?);
+        Ok(self)
+    }
+
+    /// A file containing data to visualize.
+    pub fn data_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.data = Source::File(path.into());
+        self
+    }
+
+    /// The format of the data, rather than the one implied by the data file.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// A SQLite database and a query on it giving the data to visualize.
+    pub fn sqlite(mut self, db: impl Into<PathBuf>, query: impl Into<String>) -> Self {
+        self.data = Source::Sqlite {
+            db: db.into(),
+            query: query.into(),
+        };
+        self
+    }
+
+    /// A named dataset in a file, served at `/data/<name>`.
+    pub fn dataset(mut self, name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        self.datasets.push((name.into(), Source::File(path.into())));
+        self
+    }
+
+    /// A named dataset of rows that serialize to JSON records, served at `/data/<name>`.
+    pub fn dataset_rows<T: Serialize>(
+        mut self,
+        name: impl Into<String>,
+        rows: &[T],
+    ) -> serde_json::Result<Self> {
+        self.datasets
+            .push((name.into(), Source::Bytes(serde_json::to_vec(rows)?)));
+        Ok(self)
+    }
+
+    /// Stream newline delimited JSON records from stdin into the view,
+    /// keeping the most recent `window` of them if given.
+    pub fn stream(mut self, window: Option<usize>) -> Self {
+        self.stream = true;
+        self.window = window;
+        self
+    }
+
+    /// Serve arrow or parquet data to the page as Arrow IPC rather than JSON.
+    pub fn serve_arrow(mut self, serve_arrow: bool) -> Self {
+        self.serve_arrow = serve_arrow;
+        self
+    }
+
+    /// The window title.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// When sent to a vega-view server, show the view in its window of this name,
+    /// replacing the view there, rather than in a new window.
+    pub fn target(mut self, name: impl Into<String>) -> Self {
+        self.target = Some(name.into());
+        self
+    }
+
+    /// The window size (default is 1000 by 800).
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Render the view to an svg or png file in a hidden window, which needs a display.
+    /// On machines without a GPU, set `WEBKIT_DISABLE_COMPOSITING_MODE=1` in the
+    /// environment for software rendering, before any threads are started.
+    pub fn output(mut self, path: impl Into<PathBuf>) -> Self {
+        self.output = Some(path.into());
+        self
+    }
+
+    /// Treat fields in the spec that are not in the data as errors rather than warnings.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Close the window as soon as the page reports an error.
+    pub fn exit_on_error(mut self, exit_on_error: bool) -> Self {
+        self.exit_on_error = exit_on_error;
+        self
+    }
+
+    /// Give the data rows picked with the chart's selection parameters
+    /// when the window is closed or Enter is pressed.
+    pub fn select(mut self, select: bool) -> Self {
+        self.select = select;
+        self
+    }
+
+    /// Reload the view when the spec, page, script or data files change.
+    pub fn watch(mut self, watch: bool) -> Self {
+        self.watch = watch;
+        self
+    }
+
+    /// Turn on debug logging.
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Whether a spec has been given.
+    pub fn has_spec(&self) -> bool {
+        self.spec.is_some() || self.spec_file.is_some()
+    }
+
+    /// Whether the data is read from stdin.
+    pub fn reads_stdin(&self) -> bool {
+        matches!(self.data, Source::Stdin)
+    }
+
+    /// Use a spec suited to the data.
+    pub fn infer_spec(self) -> Result<Self, String> {
+        if self.stream {
+            return Err("a spec is required when streaming".to_string());
+        }
+        let records = self.data_records().map_err(|e| e.to_string())?;
+        let records = match records {
+            Value::Array(records) => records,
+            _ => return Err("unable to infer a spec, the data is not an array".to_string()),
+        };
+        Ok(self.spec_value(&infer::spec(&records)?))
+    }
+
+    /// The JSON text of the specification, given directly or in a file.
+    /// Placeholders in the text are filled from the template variables.
+    fn spec_text(&self) -> Result<String, String> {
+        let text = match &self.spec_file {
+            Some(path) if path == Path::new("-") => all_input(),
+            Some(path) => file_contents(path).map_err(|e| e.to_string())?,
+            None => self.spec.clone().unwrap_or_default().into_bytes(),
+        };
+        let yaml = self.spec_file.as_deref().is_some_and(spec::is_yaml);
+        let text = template::fill(&String::from_utf8_lossy(&text), &self.var, yaml)?;
+        match &self.spec_file {
+            Some(path) => spec::to_json(path, text),
+            None => Ok(text),
+        }
+    }
+
+    /// The language of a specification, explicit or detected.
+    fn mode_of(&self, spec: &Value) -> Mode {
+        self.mode.unwrap_or_else(|| Mode::detect(spec))
+    }
+
+    /// The specification with its properties set,
+    /// its named data linked to the datasets and, when streaming,
+    /// its data replaced by the stream.
+    /// The data of a Vega specification without a source is linked to `/data`.
+    pub fn linked_spec(&self) -> Result<String, String> {
+        let text = self.spec_text()?;
+        let mut spec = spec::parse(&text);
+        let vega = self.mode_of(&spec) == Mode::Vega;
+        if spec.is_null()
+            || (!vega
+                && self.config.is_none()
+                && self.set.is_empty()
+                && self.datasets.is_empty()
+                && !self.stream)
+        {
+            return Ok(text);
+        }
+        if let Some(path) = &self.config {
+            let text = file_contents(path).map_err(|e| e.to_string())?;
+            let text = String::from_utf8_lossy(&text).into_owned();
+            let config = serde_json::from_str(&spec::to_json(path, text)?)
+                .map_err(|e| format!("the config is not valid JSON: {e}"))?;
+            spec::merge_config(&mut spec, &config);
+        }
+        for patch in &self.set {
+            patch.apply(&mut spec)?;
+        }
+        let names = self
+            .datasets
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        if vega {
+            spec::link_vega_data(&mut spec, &names);
+            return Ok(spec.to_string());
+        }
+        spec::link_datasets(&mut spec, &names);
+        if self.stream {
+            spec::name_data(&mut spec, stream::NAME);
+        }
+        Ok(spec.to_string())
+    }
+
+    /// The linked specification as served at `/spec`, with its urls
+    /// under the base path of the view, if it has one.
+    fn served_spec(&self) -> Result<String, String> {
+        let text = self.linked_spec()?;
+        let Some(base) = &self.base else {
+            return Ok(text);
+        };
+        let mut spec = spec::parse(&text);
+        if spec.is_null() {
+            return Ok(text);
+        }
+        spec::base_urls(&mut spec, base);
+        Ok(spec.to_string())
+    }
+
+    /// The files that make up the view.
+    pub(crate) fn files(&self) -> Vec<PathBuf> {
+        let data = [&self.data]
+            .into_iter()
+            .chain(self.datasets.iter().map(|(_, source)| source))
+            .filter_map(|source| match source {
+                Source::File(path) | Source::Sqlite { db: path, .. } => Some(path),
+                _ => None,
+            });
+        [&self.spec_file, &self.config, &self.page, &self.script]
+            .into_iter()
+            .flatten()
+            .chain(data)
+            .cloned()
+            .collect()
+    }
+
+    /// Check the spec is JSON that conforms to the vega-lite schema and refers to fields
+    /// in the data, giving any problems.  A missing schema is a problem if it is required.
+    pub fn check(&self, require_schema: bool) -> Vec<Problem> {
+        let text = match self.linked_spec() {
+            Ok(text) => text,
+            Err(e) => return vec![Problem::Error(e)],
+        };
+        let spec = match serde_json::from_str(&text) {
+            Ok(spec) => spec,
+            Err(e) => return vec![Problem::Error(format!("the spec is not valid JSON: {e}"))],
+        };
+        if self.serve_arrow && self.page.is_none() && self.script.is_none() {
+            return vec![Problem::Error(
+                "serving arrow data needs a page or script that registers vega's arrow loader"
+                    .to_string(),
+            )];
+        }
+        if self.mode_of(&spec) == Mode::Vega {
+            // only vega-lite specs are validated
+            if self.stream {
+                return vec![Problem::Error(
+                    "streaming needs a vega-lite spec".to_string(),
+                )];
+            }
+            return Vec::new();
+        }
+        match schema::validate(&spec) {
+            Some(problems) if problems.is_empty() => {}
+            Some(problems) => {
+                return problems
+                    .into_iter()
+                    .map(|problem| Problem::Error(problem.to_string()))
+                    .collect()
+            }
+            None if require_schema => {
+                return vec![Problem::Error(
+                    "vega-view was built without the vega-lite schema".to_string(),
+                )]
+            }
+            None => {}
+        }
+        self.check_fields(&spec)
+    }
+
+    /// Check the fields used by the spec are in the data.
+    fn check_fields(&self, spec: &Value) -> Vec<Problem> {
+        if self.stream || !spec::uses_data(spec) {
+            return Vec::new();
+        }
+        let columns = match self.data_columns() {
+            Ok(columns) => columns,
+            // data problems are reported when it is served
+            Err(_) => return Vec::new(),
+        };
+        lint::unknown_fields(spec, &columns)
+            .into_iter()
+            .map(|field| match self.strict {
+                true => Problem::Error(field.to_string()),
+                false => Problem::Warning(field.to_string()),
+            })
+            .collect()
+    }
+
+    /// The names of the columns of the data, from its header, schema or query,
+    /// without converting it.
+    fn data_columns(&self) -> Result<Vec<String>, data::Error> {
+        let bytes = match &self.data {
+            Source::Sqlite { db, query } => return data::query_columns(db, query),
+            Source::Stdin => all_input(),
+            Source::File(path) => file_contents(path)?,
+            Source::Bytes(bytes) => bytes.clone(),
+        };
+        data::columns(self.data.format(self.format), &bytes)
+    }
+
+    /// The data to visualize as JSON.
+    pub(crate) fn data_records(&self) -> Result<Value, data::Error> {
+        Ok(serde_json::from_slice(
+            &self.source_body(&self.data, self.format, false)?.0,
+        )?)
+    }
+
+    /// The page with everything it loads from vega-view inlined.
+    pub fn standalone_html(&self) -> Result<String, data::Error> {
+        let page = match &self.page {
+            Some(path) => file_contents(path)?,
+            None => PAGE.to_vec(),
+        };
+        let script = match &self.script {
+            Some(path) => file_contents(path)?,
+            None => SCRIPT.to_vec(),
+        };
+        Ok(export::html(
+            &String::from_utf8_lossy(&page),
+            &String::from_utf8_lossy(&script),
+            &self.inlined_spec()?,
+            &self.embed_options(),
+        )?)
+    }
+
+    /// The linked specification with the data it loads from vega-view inlined.
+    pub(crate) fn inlined_spec(&self) -> Result<Value, data::Error> {
+        let text = self
+            .linked_spec()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut spec = spec::parse(&text);
+        spec::inline_data(&mut spec, &mut |url| -> Result<_, data::Error> {
+            if url == "/data" {
+                return Ok(Some(self.data_records()?));
+            }
+            let dataset = url
+                .strip_prefix("/data/")
+                .and_then(|name| self.datasets.iter().find(|(n, _)| n == name));
+            match dataset {
+                Some((_, source)) => Ok(Some(serde_json::from_slice(
+                    &self.source_body(source, None, false)?.0,
+                )?)),
+                None => Ok(None),
+            }
+        })?;
+        Ok(spec)
+    }
+
+    /// The options for vega-embed, served at `/options`.
+    pub(crate) fn embed_options(&self) -> Value {
+        let spec = spec::parse(&self.spec_text().unwrap_or_default());
+        let mut options = json!({ "mode": self.mode_of(&spec).name() });
+        if let Some(theme) = self.theme.and_then(Theme::name) {
+            options["theme"] = json!(theme);
+        }
+        options
+    }
+
+    /// Respond to a local http request.
+    pub(crate) fn handler(
+        &self,
+        log: Log,
+        request: Request<Vec<u8>>,
+    ) -> Response<Cow<'static, [u8]>> {
+        log.print(&request);
+        match *request.method() {
+            Method::GET => match request.uri().path() {
+                "/page" => {
+                    let body = match &self.page {
+                        Some(path) => file_contents(path).map(Cow::from),
+                        None => Ok(Cow::from(PAGE)),
+                    };
+                    let body = match (body, &self.base) {
+                        (Ok(body), Some(base)) => {
+                            Ok(Cow::from(based_page(&body, base).into_bytes()))
+                        }
+                        (body, _) => body,
+                    };
+                    file_response(body, "text/html")
+                }
+                "/script" => {
+                    let body = match &self.script {
+                        Some(path) => file_contents(path).map(Cow::from),
+                        None => Ok(Cow::from(SCRIPT)),
+                    };
+                    file_response(body, "text/javascript")
+                }
+                "/spec" => match self.served_spec() {
+                    Ok(text) => Response::builder()
+                        .header("Content-Type", "application/json")
+                        .body(Cow::from(text.into_bytes()))
+                        .unwrap(),
+                    Err(e) => {
+                        eprintln!("{e}");
+                        Response::builder()
+                            .status(StatusCode::INTERNAL_SERVER_ERROR)
+                            .body(Cow::from(e.into_bytes()))
+                            .unwrap()
+                    }
+                },
+                "/options" => {
+                    let body = Cow::from(self.embed_options().to_string().into_bytes());
+                    Response::builder()
+                        .header("Content-Type", "application/json")
+                        .body(body)
+                        .unwrap()
+                }
+                "/data" => data_response(
+                    log,
+                    self.source_body(&self.data, self.format, self.serve_arrow),
+                ),
+                path => {
+                    let dataset = path
+                        .strip_prefix("/data/")
+                        .and_then(|name| self.datasets.iter().find(|(n, _)| n == name));
+                    if let Some((_, source)) = dataset {
+                        data_response(log, self.source_body(source, None, self.serve_arrow))
+                    } else {
+                        Response::builder()
+                            .status(StatusCode::NOT_FOUND)
+                            .body(Cow::from("Not found".as_bytes()))
+                            .unwrap()
+                    }
+                }
+            },
+            _ => Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .body(Cow::from("Wrong method".as_bytes()))
+                .unwrap(),
+        }
+    }
+
+    /// Data from a source and its content type, as served to the page.
+    fn source_body(
+        &self,
+        source: &Source,
+        format: Option<Format>,
+        arrow: bool,
+    ) -> Result<(Vec<u8>, &'static str), data::Error> {
+        let bytes = match source {
+            Source::Sqlite { db, query } => {
+                return Ok((data::query(db, query)?, "application/json"))
+            }
+            Source::Stdin => all_input(),
+            Source::File(path) => file_contents(path)?,
+            Source::Bytes(bytes) => bytes.clone(),
+        };
+        let format = source.format(format);
+        let spec = spec::parse(&self.spec_text().unwrap_or_default());
+        if arrow {
+            let body = data::to_arrow(format, bytes, &spec)?;
+            Ok((body, "application/vnd.apache.arrow.file"))
+        } else {
+            let body = data::to_json(format, bytes, &spec)?;
+            Ok((body, "application/json"))
+        }
+    }
+}
+
+/// A page loading its script, spec, options and anything else from vega-view
+/// from the urls of a view served under a base path: each string that is exactly
+/// the url of a route of vega-view is prefixed with the base.
+fn based_page(page: &[u8], base: &str) -> String {
+    let page = String::from_utf8_lossy(page);
+    let mut based = String::with_capacity(page.len());
+    let mut rest = &*page;
+    while let Some(i) = rest.find(['\'', '"', '`']) {
+        let quote = &rest[i..i + 1];
+        let (before, after) = rest.split_at(i + 1);
+        based.push_str(before);
+        // a closing quote is not followed by a route and its own closing quote
+        if after.find(quote).is_some_and(|end| is_route(&after[..end])) {
+            based.push_str(base);
+        }
+        rest = after;
+    }
+    based.push_str(rest);
+    based
+}
+
+/// Whether a url is one that vega-view serves.
+pub(crate) fn is_route(url: &str) -> bool {
+    match url.strip_prefix("/data/") {
+        Some(name) => {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        }
+        None => ["/page", "/script", "/spec", "/options", "/data"].contains(&url),
+    }
+}
+
+/// Respond with a page or script file or the reason it could not be read.
+fn file_response(
+    result: io::Result<Cow<'static, [u8]>>,
+    content_type: &str,
+) -> Response<Cow<'static, [u8]>> {
+    match result {
+        Ok(body) => Response::builder()
+            .header("Content-Type", content_type)
+            .body(body)
+            .unwrap(),
+        Err(e) => {
+            eprintln!("{e}");
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Cow::from(e.to_string().into_bytes()))
+                .unwrap()
+        }
+    }
+}
+
+/// Respond with data or the reason it could not be read.
+fn data_response(
+    log: Log,
+    result: Result<(Vec<u8>, &'static str), data::Error>,
+) -> Response<Cow<'static, [u8]>> {
+    match result {
+        Ok((body, content_type)) => {
+            log.print(format!("Data Length {}", body.len()));
+            Response::builder()
+                .header("Content-Type", content_type)
+                .body(Cow::from(body))
+                .unwrap()
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Cow::from(e.to_string().into_bytes()))
+                .unwrap()
+        }
+    }
+}
+
+/// All the bytes from stdin, read once so the page can be reloaded.
+fn all_input() -> Vec<u8> {
+    static INPUT: OnceLock<Vec<u8>> = OnceLock::new();
+    INPUT
+        .get_or_init(|| {
+            let mut buf = Vec::<u8>::new();
+            let _n = stdin().read_to_end(&mut buf).expect("unable to read stdin");
+            buf
+        })
+        .clone()
+}
+
+/// All the bytes in a file, or why they could not be read.
+fn file_contents(path: &Path) -> io::Result<Vec<u8>> {
+    fs::read(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("unable to read {}: {e}", path.display())))
+}
+
+/// A pimitive logger with millisecond timestamps.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Log {
+    Enabled(Instant),
+    Disabled,
+}
+
+impl Log {
+    pub(crate) fn new(enabled: bool) -> Self {
+        if enabled {
+            Self::Enabled(Instant::now())
+        } else {
+            Self::Disabled
+        }
+    }
+    pub(crate) fn print(self, item: impl fmt::Debug) {
+        match self {
+            Self::Enabled(start) => eprintln!("{} {item:?}", start.elapsed().as_millis()),
+            Self::Disabled => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn based() {
+        let page = br#"<script src='/script'></script>
+<a href="https://example.com/x">x</a>
+<script>fetch(`/options`); vegaEmbed('#vis', "/spec"); load('//cdn/x', 'a/b')</script>"#;
+        assert_eq!(
+            based_page(page, "/view/2"),
+            r#"<script src='/view/2/script'></script>
+<a href="https://example.com/x">x</a>
+<script>fetch(`/view/2/options`); vegaEmbed('#vis', "/view/2/spec"); load('//cdn/x', 'a/b')</script>"#
+        );
+        let page = br#"<meta charset="utf-8"/><img src="a.png"/><img src='/logo.png'>
+<script>load("/data/sites"); let half = "100"/2; let path = '/data'</script>"#;
+        assert_eq!(
+            based_page(page, "/view/2"),
+            r#"<meta charset="utf-8"/><img src="a.png"/><img src='/logo.png'>
+<script>load("/view/2/data/sites"); let half = "100"/2; let path = '/view/2/data'</script>"#
+        );
+    }
+
+    #[test]
+    fn broken_spec() {
+        let view = ViewBuilder::new()
+            .spec(r#"{"mark": "bar"}"#)
+            .config("missing-config.json");
+        let request = Request::get("/spec").body(Vec::new()).unwrap();
+        let response = view.handler(Log::new(false), request);
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(String::from_utf8_lossy(response.body()).contains("missing-config.json"));
+        assert!(view.inlined_spec().is_err());
+
+        let view = ViewBuilder::new().spec(r#"{"mark": "bar"}"#);
+        let request = Request::get("/spec").body(Vec::new()).unwrap();
+        let response = view.handler(Log::new(false), request);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body().as_ref(), br#"{"mark": "bar"}"#);
+    }
+}
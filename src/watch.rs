@@ -0,0 +1,32 @@
+use std::{
+    fs,
+    path::PathBuf,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+const INTERVAL: Duration = Duration::from_millis(250);
+
+/// Poll files for modification on a background thread,
+/// calling `changed` after one or more of them changes.
+pub fn spawn(paths: Vec<PathBuf>, changed: impl Fn() + Send + 'static) {
+    thread::spawn(move || {
+        let mut previous = modified(&paths);
+        loop {
+            thread::sleep(INTERVAL);
+            let current = modified(&paths);
+            if current != previous {
+                previous = current;
+                changed();
+            }
+        }
+    });
+}
+
+/// The modification times of the files, or `None` for those missing.
+fn modified(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|p| fs::metadata(p).and_then(|m| m.modified()).ok())
+        .collect()
+}
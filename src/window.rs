@@ -0,0 +1,264 @@
+use crate::{
+    export, ipc, select, stream,
+    view::{Log, ViewBuilder},
+    watch,
+};
+use serde_json::Value;
+use std::{io, sync::Arc, thread, time::Duration};
+use tao::{
+    dpi::PhysicalSize,
+    event::{Event, StartCause, WindowEvent},
+    event_loop::{ControlFlow, EventLoopBuilder},
+    platform::run_return::EventLoopExtRunReturn,
+    window::WindowBuilder,
+};
+use wry::{WebView, WebViewBuilder};
+
+pub(crate) const SCHEME: &str = "view";
+pub(crate) const BASE: &str = "view://local/page";
+
+/// The exit status when the visualization fails to render.
+pub const RENDER_ERROR: i32 = 2;
+
+/// How long to wait for an exported image.
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How a view ended.
+#[derive(Debug, Clone, Default)]
+pub struct Outcome {
+    /// The exit status: 0 when the window was closed, 1 for a failure to read
+    /// the data or write the output and [`RENDER_ERROR`] when the view failed.
+    pub code: i32,
+    /// The rows picked with the chart's selection parameters, when selecting.
+    pub selected: Option<Value>,
+    /// The positions of the picked rows in the data, when selecting.
+    pub selected_indices: Option<Vec<usize>>,
+}
+
+/// Events sent to the event loop from other threads.
+#[derive(Debug)]
+enum UserEvent {
+    /// A file has changed and the page should be reloaded.
+    Reload,
+    /// A batch of records has been streamed from stdin.
+    Rows(Vec<Value>),
+    /// A message from the page.
+    Message(ipc::Message),
+    /// The page took too long to render an image.
+    Timeout,
+}
+
+impl ViewBuilder {
+    /// Show the view in a window, returning when the window is closed.
+    /// This must be called on the main thread.
+    pub fn run(self) -> wry::Result<Outcome> {
+        show(self, false)
+    }
+
+    /// Show the view in a window from a new thread, which gives how the view ended.
+    /// Only one thread at a time may show views.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "windows",
+    ))]
+    pub fn spawn(self) -> thread::JoinHandle<wry::Result<Outcome>> {
+        thread::spawn(move || show(self, true))
+    }
+}
+
+/// Run an event loop with a window for the view until it closes.
+fn show(view: ViewBuilder, any_thread: bool) -> wry::Result<Outcome> {
+    let log = Log::new(view.debug);
+    let export = match &view.output {
+        Some(path) => match export::Image::from_path(path) {
+            Some(image) => Some((path.clone(), image)),
+            None => {
+                eprintln!("error: {} is not an .svg or .png file", path.display());
+                return Ok(Outcome {
+                    code: 1,
+                    ..Outcome::default()
+                });
+            }
+        },
+        None => None,
+    };
+    let mut builder = EventLoopBuilder::<UserEvent>::with_user_event();
+    if any_thread {
+        any_thread_builder(&mut builder);
+    }
+    let mut event_loop = builder.build();
+    let window = WindowBuilder::new()
+        .with_title(view.title.as_deref().unwrap_or("Vega View"))
+        .with_inner_size(PhysicalSize::new(view.width, view.height))
+        .with_decorations(true)
+        .with_visible(export.is_none())
+        .with_theme(
+            view.theme
+                .filter(|t| t.is_dark())
+                .map(|_| tao::window::Theme::Dark),
+        )
+        .build(&event_loop)
+        .map_err(|e| io::Error::other(e.to_string()))?;
+    let mut view = view;
+    view.theme = view.theme.map(|theme| theme.resolve(window.theme()));
+    let view = Arc::new(view);
+    if view.watch {
+        let proxy = event_loop.create_proxy();
+        watch::spawn(view.files(), move || {
+            let _ = proxy.send_event(UserEvent::Reload);
+        });
+    }
+    if view.stream {
+        let proxy = event_loop.create_proxy();
+        stream::spawn(move |rows| {
+            let _ = proxy.send_event(UserEvent::Rows(rows));
+        });
+    }
+    if export.is_some() {
+        let proxy = event_loop.create_proxy();
+        thread::spawn(move || {
+            thread::sleep(EXPORT_TIMEOUT);
+            let _ = proxy.send_event(UserEvent::Timeout);
+        });
+    }
+    let mut selections = select::Selections::default();
+    let mut buffer = stream::Buffer::new(view.window);
+    let mut outcome = Outcome::default();
+    let proxy = event_loop.create_proxy();
+    let served = view.clone();
+    let webview = WebViewBuilder::new(&window)
+        .with_custom_protocol(SCHEME.to_string(), move |r| served.handler(log, r))
+        .with_ipc_handler(move |r| match ipc::Message::parse(r.body()) {
+            Ok(message) => {
+                let _ = proxy.send_event(UserEvent::Message(message));
+            }
+            Err(e) => eprintln!("invalid message from page: {e}"),
+        })
+        .with_url(BASE)
+        .with_devtools(true)
+        .build()?;
+
+    let code = event_loop.run_return(|event, _, control_flow| {
+        *control_flow = ControlFlow::Wait;
+
+        match event {
+            Event::NewEvents(StartCause::WaitCancelled { .. }) => {}
+            Event::MainEventsCleared => {}
+            Event::RedrawEventsCleared => {}
+            Event::DeviceEvent { .. } => {}
+            Event::UserEvent(UserEvent::Reload) => {
+                log.print(&event);
+                run_script(&webview, "location.reload()");
+            }
+            Event::UserEvent(UserEvent::Rows(rows)) => {
+                log.print(format!("Streamed {}", rows.len()));
+                if let Some(script) = buffer.push(rows) {
+                    run_script(&webview, &script);
+                }
+            }
+            Event::UserEvent(UserEvent::Message(ipc::Message::Ready)) => {
+                log.print(&event);
+                if let Some((_, image)) = &export {
+                    run_script(&webview, image.script());
+                } else if view.stream {
+                    run_script(&webview, &buffer.ready());
+                }
+            }
+            Event::UserEvent(UserEvent::Message(ipc::Message::Image { data })) => {
+                log.print(format!("Image Length {}", data.len()));
+                if let Some((path, image)) = &export {
+                    *control_flow = match image.write(path, &data) {
+                        Ok(()) => ControlFlow::Exit,
+                        Err(e) => {
+                            eprintln!("unable to write {}: {e}", path.display());
+                            ControlFlow::ExitWithCode(1)
+                        }
+                    };
+                }
+            }
+            Event::UserEvent(UserEvent::Message(ipc::Message::Error { message })) => {
+                eprintln!("error: {message}");
+                if export.is_some() || view.exit_on_error {
+                    *control_flow = ControlFlow::ExitWithCode(RENDER_ERROR);
+                }
+            }
+            Event::UserEvent(UserEvent::Message(ipc::Message::Warning { message })) => {
+                eprintln!("warning: {message}");
+            }
+            Event::UserEvent(UserEvent::Message(ipc::Message::Selection { name, store })) => {
+                log.print(format!("Selection {name} {}", store.len()));
+                selections.update(name, store);
+            }
+            Event::UserEvent(UserEvent::Message(ipc::Message::Submit)) => {
+                if view.select {
+                    *control_flow = select_rows(&view, &selections, &mut outcome);
+                }
+            }
+            Event::UserEvent(UserEvent::Timeout) => {
+                eprintln!("error: timed out rendering the view");
+                *control_flow = ControlFlow::ExitWithCode(RENDER_ERROR);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => {
+                *control_flow = match view.select {
+                    true => select_rows(&view, &selections, &mut outcome),
+                    false => ControlFlow::Exit,
+                };
+                log.print(&event)
+            }
+            _ => log.print(&event),
+        }
+    });
+    Ok(Outcome { code, ..outcome })
+}
+
+/// Allow the event loop to run on a thread other than the main thread.
+#[allow(unused_variables)]
+fn any_thread_builder(builder: &mut EventLoopBuilder<UserEvent>) {
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+    ))]
+    tao::platform::unix::EventLoopBuilderExtUnix::with_any_thread(builder, true);
+    #[cfg(target_os = "windows")]
+    tao::platform::windows::EventLoopBuilderExtWindows::with_any_thread(builder, true);
+}
+
+/// Keep the selected data rows, giving how to exit.
+fn select_rows(
+    view: &ViewBuilder,
+    selections: &select::Selections,
+    outcome: &mut Outcome,
+) -> ControlFlow {
+    match view.data_records() {
+        Ok(Value::Array(records)) => {
+            outcome.selected_indices = Some(selections.indices(&records));
+            outcome.selected = Some(Value::Array(selections.select(records)));
+            ControlFlow::Exit
+        }
+        Ok(_) => {
+            eprintln!("error: data is not an array of records");
+            ControlFlow::ExitWithCode(1)
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            ControlFlow::ExitWithCode(1)
+        }
+    }
+}
+
+/// Run a script in the page, reporting failure.
+pub(crate) fn run_script(webview: &WebView, script: &str) {
+    if let Err(e) = webview.evaluate_script(script) {
+        eprintln!("unable to run script in page: {e}");
+    }
+}
@@ -0,0 +1,65 @@
+//! The preset chart subcommands and the charts of `vega.nu` give the specs in
+//! `tests/charts.json`.  The `vega.nu` side is skipped when `nu` is not installed.
+
+use clap::Parser;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{io, process::Command};
+use vega_view::chart::{self, Chart};
+
+#[derive(Parser)]
+struct Args {
+    #[command(subcommand)]
+    chart: Chart,
+
+    #[arg(long, global = true)]
+    flip: bool,
+}
+
+/// A chart made with `vega.nu` and with the arguments of vega-view, and its spec.
+#[derive(Deserialize)]
+struct Case {
+    nu: String,
+    args: String,
+    spec: Value,
+}
+
+fn cases() -> Vec<Case> {
+    serde_json::from_str(include_str!("charts.json")).unwrap()
+}
+
+#[test]
+fn subcommands() {
+    for case in cases() {
+        let args = ["vega-view"].into_iter().chain(case.args.split(' '));
+        let args = Args::try_parse_from(args).unwrap();
+        let mut spec = args.chart.spec();
+        if args.flip {
+            chart::flip(&mut spec);
+        }
+        assert_eq!(spec.to_string(), case.spec.to_string(), "{}", case.args);
+    }
+}
+
+#[test]
+fn nushell_module() {
+    for case in cases() {
+        let script = format!("use vega.nu; {} | to json --raw", case.nu);
+        let output = match Command::new("nu")
+            .args(["--no-config-file", "--commands", &script])
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .output()
+        {
+            Ok(output) => output,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                eprintln!("nu is not installed, vega.nu is not checked");
+                return;
+            }
+            Err(e) => panic!("unable to run nu: {e}"),
+        };
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{}: {stderr}", case.nu);
+        let spec: Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(spec.to_string(), case.spec.to_string(), "{}", case.nu);
+    }
+}
@@ -0,0 +1,152 @@
+//! The typed vega-lite charts serialize to specs that conform to the schema
+//! and read back as the same charts.
+
+use serde_json::json;
+use vega_view::{
+    chart::Chart as Preset,
+    schema,
+    vegalite::{
+        Aggregate, Aggregated, Axis, Channel, Chart, Data, Encoding, Facet, FieldType, Mark,
+        MarkType, Size, TimeUnit, Transform,
+    },
+};
+
+/// Check the chart against the bundled schema, when there is one,
+/// and that it reads back unchanged.
+fn round_trip(chart: &Chart) {
+    let spec = chart.to_value();
+    match schema::validate(&spec) {
+        Some(problems) => {
+            let problems: Vec<_> = problems.iter().map(|p| p.to_string()).collect();
+            assert!(problems.is_empty(), "{spec:#}\n{}", problems.join("\n"));
+        }
+        None => eprintln!("vega-view was built without the schema, run download-vega.nu"),
+    }
+    let read: Chart = serde_json::from_value(spec).unwrap();
+    assert_eq!(&read, chart);
+}
+
+#[test]
+fn presets() {
+    let presets = [
+        Preset::Bar {
+            value: "price".into(),
+            category: Some("make".into()),
+            subcategory: Some("model".into()),
+            aggregate: Aggregate::Mean,
+        },
+        Preset::Series {
+            value: "price".into(),
+            time: "date".into(),
+            category: Some("make".into()),
+            area: true,
+        },
+        Preset::Scatter {
+            value: "price".into(),
+            domain: "weight".into(),
+            category: Some("make".into()),
+        },
+    ];
+    for preset in presets {
+        round_trip(&preset.chart().data(Data::url("/data")));
+    }
+}
+
+#[test]
+fn bar() {
+    let preset = Preset::Bar {
+        value: "price".into(),
+        category: Some("make".into()),
+        subcategory: None,
+        aggregate: Aggregate::Sum,
+    };
+    assert_eq!(
+        preset.spec().to_string(),
+        json!({
+            "$schema": "https://vega.github.io/schema/vega-lite/v5.json",
+            "mark": { "type": "bar", "tooltip": true },
+            "width": "container",
+            "encoding": {
+                "x": { "field": "make", "type": "nominal" },
+                "y": {
+                    "aggregate": "sum",
+                    "field": "price",
+                    "type": "quantitative",
+                    "axis": { "title": "sum of price" },
+                },
+            },
+        })
+        .to_string()
+    );
+}
+
+#[test]
+fn layered() {
+    let line = Chart::new(Mark::new(MarkType::Line).point(true)).encoding(
+        Encoding::new()
+            .x(Channel::field("date", FieldType::Temporal).time_unit(TimeUnit::YearMonth))
+            .y(Channel::field("price", FieldType::Quantitative).aggregate(Aggregate::Mean)),
+    );
+    let rule = Chart::new(Mark::new(MarkType::Rule).color("red")).encoding(
+        Encoding::new().y(Channel::field("price", FieldType::Quantitative)
+            .aggregate(Aggregate::Median)
+            .axis(Axis::titled("price"))),
+    );
+    let chart = Chart::layered([line, rule])
+        .title("Prices")
+        .data(Data::url("/data"))
+        .width(Size::Container)
+        .height(Size::Pixels(300));
+    assert!(chart.layer.iter().all(|layer| layer.schema.is_none()));
+    round_trip(&chart);
+}
+
+#[test]
+fn faceted() {
+    let spec = Chart::new(Mark::new(MarkType::Bar)).encoding(
+        Encoding::new()
+            .x(Channel::field("price", FieldType::Quantitative).bin(true))
+            .y(Channel::count()),
+    );
+    let chart = Chart::faceted(
+        Facet::default()
+            .row(Channel::field("make", FieldType::Nominal))
+            .column(Channel::field("year", FieldType::Ordinal)),
+        spec,
+    )
+    .data(Data::named("cars"));
+    round_trip(&chart);
+}
+
+#[test]
+fn transforms() {
+    let chart = Chart::new(Mark::new(MarkType::Bar).tooltip(true))
+        .data(Data::values(json!([{ "a": 1, "b": 2, "c": "x" }])))
+        .transform(Transform::Filter {
+            filter: "datum.a > 0".into(),
+        })
+        .transform(Transform::Calculate {
+            calculate: "datum.a + datum.b".into(),
+            name: "total".into(),
+        })
+        .transform(Transform::Fold {
+            fold: vec!["a".into(), "b".into()],
+            names: Some(["key".into(), "value".into()]),
+        })
+        .transform(Transform::Aggregate {
+            aggregate: vec![Aggregated {
+                op: Aggregate::Sum,
+                field: Some("value".into()),
+                name: "sum".into(),
+            }],
+            groupby: vec!["key".into(), "c".into()],
+        })
+        .encoding(
+            Encoding::new()
+                .x(Channel::field("key", FieldType::Nominal))
+                .y(Channel::field("sum", FieldType::Quantitative))
+                .color(Channel::field("c", FieldType::Nominal))
+                .tooltip(Channel::field("sum", FieldType::Quantitative)),
+        );
+    round_trip(&chart);
+}